@@ -0,0 +1,288 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::lock::lock;
+
+/// A source of time for a [`Timer`](crate::Timer).
+///
+/// A clock hands out opaque points in time and knows how to compute the span
+/// between two of them. Clocks that can never go backwards (all built-in clocks
+/// except [`SystemClock`] and [`ManualClock`], which [`set`](ManualClock::set)
+/// can move backwards) always return `Ok` from
+/// [`duration_between`](Clock::duration_between).
+pub trait Clock {
+    /// A point in time as reported by this clock.
+    type Instant: Copy;
+
+    /// Returns the current point in time.
+    fn now(&self) -> Self::Instant;
+
+    /// Returns the time passed from `earlier` to `later`.
+    ///
+    /// If `later` actually lies before `earlier`, the amount of time the clock
+    /// went backwards is returned as `Err`.
+    fn duration_between(&self, earlier: Self::Instant, later: Self::Instant) -> Result<Duration, Duration>;
+}
+
+/// Monotonic clock backed by [`std::time::Instant`]. This is the default clock
+/// of a [`Timer`](crate::Timer).
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{Clock, MonotonicClock};
+///
+/// let clock = MonotonicClock;
+/// let earlier = clock.now();
+/// let later = clock.now();
+/// assert_eq!(true, clock.duration_between(earlier, later).is_ok());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    type Instant = Instant;
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn duration_between(&self, earlier: Instant, later: Instant) -> Result<Duration, Duration> {
+        Ok(later.saturating_duration_since(earlier))
+    }
+}
+
+/// Wall clock backed by [`std::time::SystemTime`].
+///
+/// The wall clock may be adjusted at any time (e.g. by NTP), so spans measured
+/// with it can come out negative.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{Clock, SystemClock};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let clock = SystemClock;
+/// let earlier = UNIX_EPOCH + Duration::from_secs(10);
+/// let later = UNIX_EPOCH + Duration::from_secs(4);
+/// assert_eq!(Err(Duration::from_secs(6)), clock.duration_between(earlier, later));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    type Instant = SystemTime;
+
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn duration_between(&self, earlier: SystemTime, later: SystemTime) -> Result<Duration, Duration> {
+        later.duration_since(earlier).map_err(|e| e.duration())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn clock_gettime(id: libc::clockid_t) -> Duration {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // The clock ids used here are supported since Linux 2.6.39, so the call
+    // can only fail on an invalid pointer, which we never pass.
+    let result = unsafe { libc::clock_gettime(id, &mut ts) };
+    assert_eq!(0, result, "clock_gettime({}) failed", id);
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+fn duration_between(earlier: Duration, later: Duration) -> Result<Duration, Duration> {
+    if later >= earlier {
+        Ok(later - earlier)
+    } else {
+        Err(earlier - later)
+    }
+}
+
+/// Linux `CLOCK_MONOTONIC_RAW`: monotonic time that is not subject to NTP
+/// frequency adjustments.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{Clock, MonotonicRawClock};
+///
+/// let clock = MonotonicRawClock;
+/// let earlier = clock.now();
+/// let later = clock.now();
+/// assert_eq!(true, clock.duration_between(earlier, later).is_ok());
+/// ```
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MonotonicRawClock;
+
+#[cfg(target_os = "linux")]
+impl Clock for MonotonicRawClock {
+    type Instant = Duration;
+
+    fn now(&self) -> Duration {
+        clock_gettime(libc::CLOCK_MONOTONIC_RAW)
+    }
+
+    fn duration_between(&self, earlier: Duration, later: Duration) -> Result<Duration, Duration> {
+        duration_between(earlier, later)
+    }
+}
+
+/// Linux `CLOCK_BOOTTIME`: monotonic time that keeps counting while the system
+/// is suspended.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{Clock, BootTimeClock};
+///
+/// let clock = BootTimeClock;
+/// let earlier = clock.now();
+/// let later = clock.now();
+/// assert_eq!(true, clock.duration_between(earlier, later).is_ok());
+/// ```
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BootTimeClock;
+
+#[cfg(target_os = "linux")]
+impl Clock for BootTimeClock {
+    type Instant = Duration;
+
+    fn now(&self) -> Duration {
+        clock_gettime(libc::CLOCK_BOOTTIME)
+    }
+
+    fn duration_between(&self, earlier: Duration, later: Duration) -> Result<Duration, Duration> {
+        duration_between(earlier, later)
+    }
+}
+
+/// Linux `CLOCK_MONOTONIC_COARSE`: a faster but less precise monotonic clock,
+/// usually with a resolution of a few milliseconds.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{Clock, MonotonicCoarseClock};
+///
+/// let clock = MonotonicCoarseClock;
+/// let earlier = clock.now();
+/// let later = clock.now();
+/// assert_eq!(true, clock.duration_between(earlier, later).is_ok());
+/// ```
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MonotonicCoarseClock;
+
+#[cfg(target_os = "linux")]
+impl Clock for MonotonicCoarseClock {
+    type Instant = Duration;
+
+    fn now(&self) -> Duration {
+        clock_gettime(libc::CLOCK_MONOTONIC_COARSE)
+    }
+
+    fn duration_between(&self, earlier: Duration, later: Duration) -> Result<Duration, Duration> {
+        duration_between(earlier, later)
+    }
+}
+
+/// A virtual clock that only moves when told to, for deterministic tests of
+/// timed code.
+///
+/// Clones share the same time, so one handle can be plugged into a
+/// [`Timer`](crate::Timer) while another one drives it.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{ManualClock, Timer};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// let mut timer = Timer::with_clock(clock.clone());
+/// timer.start();
+/// clock.advance(Duration::from_millis(3));
+/// assert_eq!(Ok(Duration::from_millis(3)), timer.lap());
+/// clock.set(Duration::from_secs(1));
+/// assert_eq!(Ok(Duration::from_secs(1)), timer.lap());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    inner: Arc<ManualClockInner>,
+}
+
+#[derive(Debug, Default)]
+struct ManualClockInner {
+    now: Mutex<Duration>,
+    step: Duration,
+}
+
+impl ManualClock {
+    /// Creates a clock standing at zero.
+    pub fn new() -> Self {
+        ManualClock::default()
+    }
+
+    /// Creates a clock standing at zero that moves forward by `step` every time
+    /// it is read.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let mut timer = Timer::with_clock(ManualClock::auto_advancing(Duration::from_micros(10)));
+    /// timer.start();
+    /// assert_eq!(Ok(Duration::from_micros(10)), timer.lap());
+    /// assert_eq!(Ok(Duration::from_micros(20)), timer.lap());
+    /// ```
+    pub fn auto_advancing(step: Duration) -> Self {
+        ManualClock {
+            inner: Arc::new(ManualClockInner {
+                now: Mutex::new(Duration::default()),
+                step,
+            }),
+        }
+    }
+
+    /// Moves the clock forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        *self.lock() += by;
+    }
+
+    /// Sets the clock to `now`. Setting an earlier time than the current one
+    /// moves the clock backwards.
+    pub fn set(&self, now: Duration) {
+        *self.lock() = now;
+    }
+
+    /// Returns the current time without advancing an auto-advancing clock.
+    pub fn peek(&self) -> Duration {
+        *self.lock()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Duration> {
+        lock(&self.inner.now)
+    }
+}
+
+impl Clock for ManualClock {
+    type Instant = Duration;
+
+    fn now(&self) -> Duration {
+        let mut now = self.lock();
+        let current = *now;
+        *now += self.inner.step;
+        current
+    }
+
+    fn duration_between(&self, earlier: Duration, later: Duration) -> Result<Duration, Duration> {
+        duration_between(earlier, later)
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Errors returned by [`Timer`](crate::Timer) operations and other fallible
+/// functions of this crate.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{Error, Timer};
+///
+/// let mut timer = Timer::new();
+/// assert_eq!(Err(Error::NotStarted), timer.lap());
+/// assert_eq!("Timer not started!", Error::NotStarted.to_string());
+/// ```
+///
+/// ## A clock going backwards:
+/// ```
+/// use yatl::{Error, ManualClock, Timer};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// clock.set(Duration::from_secs(5));
+/// let mut timer = Timer::with_clock(clock.clone());
+/// timer.start();
+/// clock.set(Duration::from_secs(3));
+/// assert_eq!(Err(Error::ClockWentBackwards { by: Duration::from_secs(2) }), timer.lap());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The timer has already been started.
+    AlreadyStarted,
+    /// The timer has not been started yet.
+    NotStarted,
+    /// The clock reported a point in time before the start of the timer.
+    ClockWentBackwards {
+        /// How far the clock went backwards.
+        by: Duration,
+    },
+    /// The timer is already paused.
+    AlreadyPaused,
+    /// The timer is not paused.
+    NotPaused,
+    /// The timer has been stopped and needs a reset before it can be used again.
+    Stopped,
+    /// The range or precision passed to [`Histogram::new`](crate::Histogram::new)
+    /// is not supported.
+    InvalidHistogram,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::AlreadyStarted => write!(f, "Timer already started!"),
+            Error::NotStarted => write!(f, "Timer not started!"),
+            Error::ClockWentBackwards { by } => write!(f, "Clock went backwards by {:?}!", by),
+            Error::AlreadyPaused => write!(f, "Timer already paused!"),
+            Error::NotPaused => write!(f, "Timer not paused!"),
+            Error::Stopped => write!(f, "Timer stopped!"),
+            Error::InvalidHistogram => write!(f, "Invalid histogram configuration!"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Shorthand for results carrying an [`Error`].
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,218 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::{pin, Pin};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use crate::lock::{lock, wait};
+use crate::FutureExt;
+
+/// Number of threads polling spawned tasks.
+const WORKERS: usize = 2;
+
+static POOL: OnceLock<Arc<Queue>> = OnceLock::new();
+
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Runs `future` to completion on the current thread.
+///
+/// Together with [`spawn`] this is a minimal executor to try out the async
+/// support of this crate without depending on a runtime. It is only available
+/// with the `executor` feature and not meant for production use.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{block_on, spawn};
+///
+/// let answer = block_on(async {
+///     spawn(async { 6 * 7 }).await.unwrap()
+/// });
+/// assert_eq!(42, answer);
+/// ```
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs `future` on a small pool of worker threads. Each time the task is
+/// woken up it may continue on a different thread.
+///
+/// The future is polled in the spans open when it is spawned, see
+/// [`FutureExt::in_current_span`].
+pub fn spawn<T, F>(future: F) -> JoinHandle<T>
+where
+    T: Send + 'static,
+    F: Future<Output = T> + Send + 'static,
+{
+    let join = Arc::new(Join {
+        state: Mutex::new(JoinState {
+            output: None,
+            waker: None,
+        }),
+        finished: Condvar::new(),
+    });
+    let task_join = Arc::clone(&join);
+    let mut future = Box::pin(future.in_current_span());
+    let task: BoxFuture = Box::pin(std::future::poll_fn(move |context| {
+        let poll = panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(context)));
+        let output = match poll {
+            Ok(Poll::Pending) => return Poll::Pending,
+            Ok(Poll::Ready(output)) => Ok(output),
+            Err(panic) => Err(panic),
+        };
+        let mut state = lock(&task_join.state);
+        state.output = Some(output);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        task_join.finished.notify_all();
+        Poll::Ready(())
+    }));
+
+    let queue = POOL.get_or_init(start_workers);
+    queue.push(Arc::new(Task {
+        future: Mutex::new(Some(task)),
+        queue: Arc::clone(queue),
+    }));
+    JoinHandle { join }
+}
+
+/// Handle to wait for a task started with [`spawn`].
+///
+/// Awaiting the handle or calling [`join`](JoinHandle::join) returns the
+/// output of the task, or the payload of its panic as `Err`, like
+/// [`std::thread::JoinHandle::join`].
+pub struct JoinHandle<T> {
+    join: Arc<Join<T>>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Blocks the current thread until the task is finished.
+    pub fn join(self) -> thread::Result<T> {
+        let mut state = lock(&self.join.state);
+        loop {
+            if let Some(output) = state.output.take() {
+                return output;
+            }
+            state = wait(&self.join.finished, state);
+        }
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = thread::Result<T>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = lock(&self.join.state);
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.waker = Some(context.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+struct Join<T> {
+    state: Mutex<JoinState<T>>,
+    finished: Condvar,
+}
+
+struct JoinState<T> {
+    output: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// Yields once to the executor, which polls the task again later.
+pub async fn yield_now() {
+    let mut yielded = false;
+    std::future::poll_fn(|context| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        context.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
+
+struct Task {
+    /// `None` once the task is finished.
+    future: Mutex<Option<BoxFuture>>,
+    queue: Arc<Queue>,
+}
+
+impl Task {
+    fn run(self: Arc<Self>) {
+        let waker = Waker::from(Arc::clone(&self));
+        let mut context = Context::from_waker(&waker);
+        let mut future = lock(&self.future);
+        if let Some(Poll::Ready(())) = future.as_mut().map(|future| future.as_mut().poll(&mut context)) {
+            *future = None;
+        }
+    }
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        let queue = Arc::clone(&self.queue);
+        queue.push(self);
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    tasks: Mutex<VecDeque<Arc<Task>>>,
+    available: Condvar,
+}
+
+impl Queue {
+    fn push(&self, task: Arc<Task>) {
+        lock(&self.tasks).push_back(task);
+        self.available.notify_one();
+    }
+
+    fn pop(&self) -> Arc<Task> {
+        let mut tasks = lock(&self.tasks);
+        loop {
+            if let Some(task) = tasks.pop_front() {
+                return task;
+            }
+            tasks = wait(&self.available, tasks);
+        }
+    }
+}
+
+fn start_workers() -> Arc<Queue> {
+    let queue = Arc::new(Queue::default());
+    for worker in 0..WORKERS {
+        let queue = Arc::clone(&queue);
+        thread::Builder::new()
+            .name(format!("yatl-worker-{}", worker))
+            .spawn(move || loop {
+                queue.pop().run();
+            })
+            .expect("failed to start worker thread");
+    }
+    queue
+}
@@ -0,0 +1,286 @@
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::sync::Exemplar;
+use crate::{Histogram, Registry};
+
+impl Registry {
+    /// Renders all timers in the Prometheus text format (version 0.0.4).
+    ///
+    /// Every timer becomes a histogram, or a summary if
+    /// [quantiles](Registry::set_quantiles) are set, in seconds. Its name is
+    /// the timer name with every character not allowed by Prometheus replaced
+    /// by `_` and `_seconds` appended, label names are sanitized the same way.
+    /// Timers whose names end up the same form one metric family, and their
+    /// histograms are merged if their labels end up the same as well. Bucket
+    /// counts and quantiles come from the
+    /// [histogram](crate::SyncTimer::histogram) of each timer, so they are
+    /// precise to three significant digits.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::Registry;
+    /// use std::time::Duration;
+    ///
+    /// let registry = Registry::new();
+    /// registry.set_buckets(vec![Duration::from_millis(10), Duration::from_secs(1)]);
+    /// let get = registry.labeled("http.request", &[("method", "GET")]);
+    /// get.record(Duration::from_millis(4));
+    /// get.record(Duration::from_millis(250));
+    ///
+    /// assert_eq!(
+    ///     "# HELP http_request_seconds Durations of http.request.\n\
+    ///      ## TYPE http_request_seconds histogram\n\
+    ///      http_request_seconds_bucket{method=\"GET\",le=\"0.01\"} 1\n\
+    ///      http_request_seconds_bucket{method=\"GET\",le=\"1.0\"} 2\n\
+    ///      http_request_seconds_bucket{method=\"GET\",le=\"+Inf\"} 2\n\
+    ///      http_request_seconds_sum{method=\"GET\"} 0.254\n\
+    ///      http_request_seconds_count{method=\"GET\"} 2\n",
+    ///     registry.render_prometheus()
+    /// );
+    ///
+    /// registry.set_quantiles(vec![0.5, 1.0]);
+    /// assert_eq!(
+    ///     "# HELP http_request_seconds Durations of http.request.\n\
+    ///      ## TYPE http_request_seconds summary\n\
+    ///      http_request_seconds{method=\"GET\",quantile=\"0.5\"} 0.004001791\n\
+    ///      http_request_seconds{method=\"GET\",quantile=\"1.0\"} 0.25\n\
+    ///      http_request_seconds_sum{method=\"GET\"} 0.254\n\
+    ///      http_request_seconds_count{method=\"GET\"} 2\n",
+    ///     registry.render_prometheus()
+    /// );
+    ///
+    /// let registry = Registry::new();
+    /// registry.timer("a.b").record(Duration::from_millis(1));
+    /// registry.timer("a_a").record(Duration::from_millis(1));
+    /// registry.labeled("a_b", &[("http.method", "GET")]).record(Duration::from_millis(1));
+    /// registry.labeled("a.b", &[("http_method", "GET")]).record(Duration::from_millis(1));
+    /// registry.labeled("a.b", &[("le", "x"), ("quantile", "y")]).record(Duration::from_millis(1));
+    /// let metrics = registry.render_prometheus();
+    /// assert_eq!(1, metrics.matches("# TYPE a_b_seconds ").count());
+    /// assert_eq!(1, metrics.matches("a_b_seconds_count{http_method=\"GET\"} ").count());
+    /// assert_eq!(true, metrics.contains("a_b_seconds_count{http_method=\"GET\"} 2\n"));
+    /// assert_eq!(true, metrics.contains("a_b_seconds_count 1\n"));
+    /// assert_eq!(true, metrics.contains("a_b_seconds_count{le_=\"x\",quantile_=\"y\"} 1\n"));
+    /// ```
+    pub fn render_prometheus(&self) -> String {
+        self.render(false)
+    }
+
+    /// Renders all timers in the OpenMetrics text format, like
+    /// [`render_prometheus`](Registry::render_prometheus) but with units and
+    /// with the latest exemplar (see
+    /// [`SyncTimer::record_exemplar`](crate::SyncTimer::record_exemplar)) of
+    /// each histogram bucket.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::Registry;
+    /// use std::time::Duration;
+    ///
+    /// let registry = Registry::new();
+    /// registry.set_buckets(vec![Duration::from_millis(10)]);
+    /// registry.timer("db.query").record_exemplar(Duration::from_millis(20), &[("trace_id", "4bf92f35")]);
+    ///
+    /// let metrics = registry.render_openmetrics();
+    /// assert_eq!(true, metrics.starts_with(
+    ///     "# TYPE db_query_seconds histogram\n\
+    ///      ## UNIT db_query_seconds seconds\n\
+    ///      ## HELP db_query_seconds Durations of db.query.\n\
+    ///      db_query_seconds_bucket{le=\"0.01\"} 0\n\
+    ///      db_query_seconds_bucket{le=\"+Inf\"} 1 # {trace_id=\"4bf92f35\"} 0.02 "
+    /// ));
+    /// assert_eq!(true, metrics.ends_with(
+    ///     "db_query_seconds_count 1\n\
+    ///      db_query_seconds_sum 0.02\n\
+    ///      ## EOF\n"
+    /// ));
+    /// ```
+    pub fn render_openmetrics(&self) -> String {
+        self.render(true)
+    }
+
+    fn render(&self, openmetrics: bool) -> String {
+        let buckets = self.buckets();
+        let quantiles = self.quantiles();
+        let kind = if quantiles.is_empty() { "histogram" } else { "summary" };
+        let mut families: BTreeMap<String, Family> = BTreeMap::new();
+        for ((name, labels), timer) in self.entries() {
+            let mut labels: Vec<(String, String)> = labels.into_iter()
+                .map(|(label, value)| (sanitize(&label, false), value))
+                .collect();
+            labels.sort();
+            let family = families.entry(metric_name(&name)).or_insert_with(|| Family {
+                name,
+                series: BTreeMap::new(),
+            });
+            let exemplars = if openmetrics { timer.exemplars() } else { vec![] };
+            match family.series.entry(labels) {
+                Entry::Vacant(entry) => {
+                    entry.insert(Series {
+                        histogram: timer.histogram(),
+                        exemplars,
+                    });
+                }
+                Entry::Occupied(mut entry) => {
+                    let series = entry.get_mut();
+                    series.histogram.merge(&timer.histogram());
+                    series.exemplars.extend(exemplars);
+                    series.exemplars.sort_by_key(|exemplar| exemplar.timestamp);
+                }
+            }
+        }
+
+        let mut text = String::new();
+        for (metric, family) in families {
+            let help = format!("Durations of {}.", family.name).replace('\\', "\\\\").replace('\n', "\\n");
+            if openmetrics {
+                let _ = writeln!(text, "# TYPE {} {}", metric, kind);
+                let _ = writeln!(text, "# UNIT {} seconds", metric);
+                let _ = writeln!(text, "# HELP {} {}", metric, help);
+            } else {
+                let _ = writeln!(text, "# HELP {} {}", metric, help);
+                let _ = writeln!(text, "# TYPE {} {}", metric, kind);
+            }
+            for (labels, Series { histogram, exemplars }) in family.series {
+                if quantiles.is_empty() {
+                    // Values sharing a histogram bucket with a bound count as within it.
+                    let mut recorded = histogram.buckets().peekable();
+                    let mut count = 0;
+                    let mut lower: Option<&Duration> = None;
+                    for upper in buckets.iter().map(Some).chain(Some(None)) {
+                        while let Some(bucket) = recorded.next_if(|bucket| upper.into_iter().all(|upper| bucket.low() <= *upper)) {
+                            count += bucket.count();
+                        }
+                        let le = upper.map_or_else(|| "+Inf".to_string(), |upper| seconds(*upper));
+                        let _ = write!(text, "{}_bucket{} {}", metric, label_set(&labels, Some(("le", &le))), count);
+                        let exemplar = exemplars.iter().rev().find(|exemplar| {
+                            lower.into_iter().all(|lower| exemplar.value > *lower) && upper.into_iter().all(|upper| exemplar.value <= *upper)
+                        });
+                        if let Some(exemplar) = exemplar {
+                            write_exemplar(&mut text, exemplar);
+                        }
+                        text.push('\n');
+                        lower = upper;
+                    }
+                } else {
+                    for quantile in &quantiles {
+                        let value = if histogram.is_empty() {
+                            "NaN".to_string()
+                        } else {
+                            seconds(histogram.value_at_quantile(*quantile))
+                        };
+                        let quantile = float(*quantile);
+                        let _ = writeln!(text, "{}{} {}", metric, label_set(&labels, Some(("quantile", &quantile))), value);
+                    }
+                }
+                write_totals(&mut text, &metric, &labels, &histogram, openmetrics);
+            }
+        }
+        if openmetrics {
+            text.push_str("# EOF\n");
+        }
+        text
+    }
+}
+
+/// Series of one metric, keyed by their sanitized labels.
+struct Family {
+    /// Name of the first timer of the family, for the help text.
+    name: String,
+    series: BTreeMap<Vec<(String, String)>, Series>,
+}
+
+/// Merged samples of all timers of a family with the same sanitized labels.
+struct Series {
+    histogram: Histogram,
+    /// Exemplars of all merged timers, oldest first.
+    exemplars: Vec<Exemplar>,
+}
+
+/// Writes the `_sum` and `_count` series, in the order of the format.
+fn write_totals(text: &mut String, metric: &str, labels: &[(String, String)], histogram: &Histogram, openmetrics: bool) {
+    let labels = label_set(labels, None);
+    let sum = format!("{}_sum{} {}\n", metric, labels, seconds(histogram.sum()));
+    let count = format!("{}_count{} {}\n", metric, labels, histogram.count());
+    if openmetrics {
+        text.push_str(&count);
+        text.push_str(&sum);
+    } else {
+        text.push_str(&sum);
+        text.push_str(&count);
+    }
+}
+
+fn write_exemplar(text: &mut String, exemplar: &Exemplar) {
+    let labels = match label_set(&exemplar.labels, None) {
+        labels if labels.is_empty() => "{}".to_string(),
+        labels => labels,
+    };
+    let _ = write!(
+        text,
+        " # {} {} {}.{:03}",
+        labels,
+        seconds(exemplar.value),
+        exemplar.timestamp.as_secs(),
+        exemplar.timestamp.subsec_millis(),
+    );
+}
+
+/// `name` with every character not allowed in metric names replaced by `_`
+/// and the unit appended.
+fn metric_name(name: &str) -> String {
+    let mut metric = sanitize(name, true);
+    metric.push_str("_seconds");
+    metric
+}
+
+/// `name` with every character not allowed in names replaced by `_`. Only
+/// metric names may contain `:`.
+fn sanitize(name: &str, metric: bool) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || (metric && c == ':') { c } else { '_' })
+        .collect();
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+/// `{label="value",...}` including `extra` at the end, or nothing if there are
+/// no labels at all.
+fn label_set(labels: &[(String, String)], extra: Option<(&str, &str)>) -> String {
+    let pairs: Vec<String> = labels.iter()
+        .map(|(label, value)| (label.as_str(), value.as_str()))
+        .chain(extra)
+        .map(|(label, value)| format!("{}=\"{}\"", sanitize(label, false), escape(value)))
+        .collect();
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn seconds(duration: Duration) -> String {
+    float(duration.as_secs_f64())
+}
+
+/// Formats `value` with a decimal point, e.g. `1.0` rather than `1`.
+fn float(value: f64) -> String {
+    let formatted = value.to_string();
+    if formatted.contains(['.', 'e', 'N', 'i']) {
+        formatted
+    } else {
+        formatted + ".0"
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::{duration_to_human_string, CallTree, Node};
+
+/// Direction in which a [`FlameGraph`] grows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// Outermost spans at the bottom, children stacked on top.
+    Flame,
+    /// Outermost spans at the top, children hanging below.
+    Icicle,
+}
+
+/// Renders a [`CallTree`] as a self-contained SVG flame graph or icicle chart.
+///
+/// Every frame shows the name, inclusive time, share of the total time and
+/// number of calls as tooltip and in the details line below the chart when
+/// hovered. Colours are derived from the span names only, so the same span
+/// gets the same colour in every run.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{FlameGraph, ManualClock, Orientation, Profiler};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// let profiler = Profiler::with_clock(clock.clone());
+/// profiler.in_span("request", || {
+///     clock.advance(Duration::from_millis(1));
+///     profiler.in_span("parse", || clock.advance(Duration::from_millis(3)));
+/// });
+///
+/// let svg = FlameGraph::new().orientation(Orientation::Icicle).render(&profiler.tree());
+/// assert_eq!(true, svg.starts_with("<?xml"));
+/// assert_eq!(true, svg.contains("<title>parse (3ms, 75.00%, 1 call)</title>"));
+/// assert_eq!(svg, FlameGraph::new().orientation(Orientation::Icicle).render(&profiler.tree()));
+///
+/// let flat = FlameGraph::new().frame_height(0).render(&profiler.tree());
+/// assert_eq!(true, flat.contains(r#"height="1" fill="#));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlameGraph {
+    orientation: Orientation,
+    width: u32,
+    frame_height: u32,
+    title: String,
+}
+
+const PADDING: u32 = 10;
+const HEADER: u32 = 30;
+const FOOTER: u32 = 30;
+const FONT_SIZE: u32 = 12;
+/// Approximate width of a character at [`FONT_SIZE`].
+const CHAR_WIDTH: f64 = 7.0;
+/// Frames narrower than this many pixels are left out.
+const MIN_WIDTH: f64 = 0.1;
+
+impl FlameGraph {
+    /// A 1200 pixel wide flame graph with 16 pixel high frames.
+    pub fn new() -> Self {
+        FlameGraph {
+            orientation: Orientation::Flame,
+            width: 1200,
+            frame_height: 16,
+            title: "Flame Graph".to_string(),
+        }
+    }
+
+    pub fn orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Width of the whole image in pixels.
+    pub fn width(mut self, width: u32) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Height of each frame in pixels, at least 2 to leave a gap between the
+    /// frames.
+    pub fn frame_height(mut self, frame_height: u32) -> Self {
+        self.frame_height = frame_height.max(2);
+        self
+    }
+
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn render(&self, tree: &CallTree) -> String {
+        let total = tree.total();
+        let depth = max_depth(tree) + 1;
+        let height = HEADER + depth as u32 * self.frame_height + FOOTER;
+        let mut svg = String::new();
+        let _ = writeln!(svg, r#"<?xml version="1.0" standalone="no"?>"#);
+        let _ = writeln!(
+            svg,
+            r#"<svg version="1.1" width="{w}" height="{h}" viewBox="0 0 {w} {h}" xmlns="http://www.w3.org/2000/svg" font-family="Verdana, sans-serif" font-size="{f}">"#,
+            w = self.width,
+            h = height,
+            f = FONT_SIZE,
+        );
+        let _ = writeln!(svg, "<style>.frame:hover rect {{ stroke: black; stroke-width: 0.5; cursor: pointer; }}</style>");
+        let _ = writeln!(
+            svg,
+            "<script><![CDATA[function details(text) {{ document.getElementById(\"details\").textContent = text; }}]]></script>"
+        );
+        let _ = writeln!(svg, r##"<rect x="0" y="0" width="{}" height="{}" fill="#f8f8f8"/>"##, self.width, height);
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle" font-size="{}">{}</text>"#,
+            self.width / 2,
+            HEADER - 10,
+            FONT_SIZE + 4,
+            escape(&self.title),
+        );
+        let _ = writeln!(svg, r#"<text id="details" x="{}" y="{}"> </text>"#, PADDING, height - 10);
+
+        let scale = self.width.saturating_sub(2 * PADDING) as f64 / total.as_nanos().max(1) as f64;
+        let calls: u64 = tree.roots().map(|node| node.calls()).sum();
+        self.frame(&mut svg, "all", total, total, calls, PADDING as f64, 0, scale, height);
+        let mut x = PADDING as f64;
+        for root in tree.roots() {
+            x += self.node(&mut svg, root, total, x, 1, scale, height);
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Renders `node` and its children at `x`, returning the width of `node`.
+    #[allow(clippy::too_many_arguments)]
+    fn node(&self, svg: &mut String, node: Node<'_>, total: Duration, x: f64, depth: usize, scale: f64, height: u32) -> f64 {
+        let width = node.inclusive().as_nanos() as f64 * scale;
+        if width < MIN_WIDTH {
+            return width;
+        }
+        self.frame(svg, node.name(), node.inclusive(), total, node.calls(), x, depth, scale, height);
+        let mut child_x = x;
+        for child in node.children() {
+            child_x += self.node(svg, child, total, child_x, depth + 1, scale, height);
+        }
+        width
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn frame(&self, svg: &mut String, name: &str, inclusive: Duration, total: Duration, calls: u64, x: f64, depth: usize, scale: f64, height: u32) {
+        let width = inclusive.as_nanos() as f64 * scale;
+        let y = match self.orientation {
+            Orientation::Flame => height - FOOTER - (depth as u32 + 1) * self.frame_height,
+            Orientation::Icicle => HEADER + depth as u32 * self.frame_height,
+        };
+        let share = if total.as_nanos() == 0 {
+            0.0
+        } else {
+            inclusive.as_nanos() as f64 * 100.0 / total.as_nanos() as f64
+        };
+        let info = escape(&format!(
+            "{} ({}, {:.2}%, {} {})",
+            name,
+            duration_to_human_string(&inclusive),
+            share,
+            calls,
+            if calls == 1 { "call" } else { "calls" },
+        ));
+        let _ = writeln!(svg, r#"<g class="frame" onmouseover="details(this.querySelector('title').textContent)" onmouseout="details(' ')">"#);
+        let _ = writeln!(svg, "<title>{}</title>", info);
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{:.2}" y="{}" width="{:.2}" height="{}" fill="{}" rx="2" ry="2"/>"#,
+            x,
+            y,
+            width,
+            self.frame_height - 1,
+            colour(name),
+        );
+        let fitting = ((width - 6.0) / CHAR_WIDTH).max(0.0) as usize;
+        if fitting >= 3 {
+            let label: String = if name.chars().count() > fitting {
+                name.chars().take(fitting - 2).chain("..".chars()).collect()
+            } else {
+                name.to_string()
+            };
+            let _ = writeln!(svg, r#"<text x="{:.2}" y="{}">{}</text>"#, x + 3.0, y + self.frame_height - 4, escape(&label));
+        }
+        svg.push_str("</g>\n");
+    }
+}
+
+impl Default for FlameGraph {
+    fn default() -> Self {
+        FlameGraph::new()
+    }
+}
+
+fn max_depth(tree: &CallTree) -> usize {
+    let mut max = 0;
+    tree.walk(|_, depth| max = max.max(depth + 1));
+    max
+}
+
+/// Warm colour derived from a FNV-1a hash of `name`.
+fn colour(name: &str) -> String {
+    let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    let red = 205 + (hash % 50);
+    let green = (hash >> 16) % 230;
+    let blue = (hash >> 32) % 55;
+    format!("rgb({},{},{})", red, green, blue)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
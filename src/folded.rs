@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::{CallTree, SpanRecord};
+
+/// Unit of the sample weights in folded stacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weight {
+    Nanoseconds,
+    Microseconds,
+}
+
+impl Weight {
+    fn of(self, duration: Duration) -> u128 {
+        match self {
+            Weight::Nanoseconds => duration.as_nanos(),
+            Weight::Microseconds => duration.as_micros(),
+        }
+    }
+}
+
+/// Renders spans in the folded stack format read by `flamegraph.pl` and
+/// `inferno` (`outer;inner;innermost 1234`), weighted by the exclusive time of
+/// each stack.
+///
+/// Lines with a weight of zero are left out. Semicolons and line breaks in
+/// span names are replaced so they cannot break the format.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{FoldedStacks, ManualClock, Profiler, Weight};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// let profiler = Profiler::with_clock(clock.clone());
+/// profiler.keep_spans();
+/// for _ in 0..2 {
+///     profiler.in_span("request", || {
+///         clock.advance(Duration::from_micros(3));
+///         profiler.in_span("parse", || clock.advance(Duration::from_micros(40)));
+///     });
+/// }
+///
+/// let folded = FoldedStacks::new().weight(Weight::Microseconds);
+/// assert_eq!("request 6\nrequest;parse 80\n", folded.spans(&profiler.spans()));
+/// assert_eq!(
+///     "request;parse 40\nrequest 3\nrequest;parse 40\nrequest 3\n",
+///     folded.merge(false).spans(&profiler.spans())
+/// );
+/// assert_eq!("request 6\nrequest;parse 80\n", folded.tree(&profiler.tree()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldedStacks {
+    weight: Weight,
+    thread_prefix: bool,
+    merge: bool,
+}
+
+impl FoldedStacks {
+    /// Nanosecond weights, merged stacks and no thread prefix.
+    pub fn new() -> Self {
+        FoldedStacks {
+            weight: Weight::Nanoseconds,
+            thread_prefix: false,
+            merge: true,
+        }
+    }
+
+    pub fn weight(mut self, weight: Weight) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Starts every stack with the name of the thread it ran on, or
+    /// `thread-<id>` for unnamed threads.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{FoldedStacks, Profiler};
+    /// use std::thread;
+    ///
+    /// let profiler = Profiler::new();
+    /// profiler.keep_spans();
+    /// let worker = profiler.clone();
+    /// thread::Builder::new()
+    ///     .name("worker".to_string())
+    ///     .spawn(move || worker.in_span("job", || thread::sleep(std::time::Duration::from_micros(10))))
+    ///     .unwrap()
+    ///     .join()
+    ///     .unwrap();
+    /// let folded = FoldedStacks::new().thread_prefix(true).spans(&profiler.spans());
+    /// assert_eq!(true, folded.starts_with("worker;job "));
+    /// ```
+    pub fn thread_prefix(mut self, thread_prefix: bool) -> Self {
+        self.thread_prefix = thread_prefix;
+        self
+    }
+
+    /// Sums up identical stacks into one line, sorted by stack. Without
+    /// merging every span gets its own line, in the order they were closed.
+    pub fn merge(mut self, merge: bool) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    /// Renders spans kept by a [`Profiler`](crate::Profiler), see
+    /// [`Profiler::keep_spans`](crate::Profiler::keep_spans).
+    pub fn spans(&self, spans: &[SpanRecord]) -> String {
+        let stacks = spans.iter().map(|span| {
+            let mut frames: Vec<String> = vec![];
+            if self.thread_prefix {
+                frames.push(span.thread_name().map_or_else(|| format!("thread-{}", span.thread_id()), str::to_string));
+            }
+            frames.extend(span.path().iter().cloned());
+            (stack(&frames), span.exclusive())
+        });
+        if self.merge {
+            self.render_merged(stacks)
+        } else {
+            self.render(stacks)
+        }
+    }
+
+    /// Renders the aggregated stacks of a [`CallTree`], which are always merged
+    /// and have no thread prefix.
+    pub fn tree(&self, tree: &CallTree) -> String {
+        let mut stacks = vec![];
+        let mut path: Vec<String> = vec![];
+        tree.walk(|node, depth| {
+            path.truncate(depth);
+            path.push(node.name().to_string());
+            stacks.push((stack(&path), node.exclusive()));
+        });
+        self.render_merged(stacks.into_iter())
+    }
+
+    fn render_merged<I: Iterator<Item = (String, Duration)>>(&self, stacks: I) -> String {
+        let mut merged: BTreeMap<String, Duration> = BTreeMap::new();
+        for (stack, exclusive) in stacks {
+            *merged.entry(stack).or_default() += exclusive;
+        }
+        self.render(merged.into_iter())
+    }
+
+    fn render<I: Iterator<Item = (String, Duration)>>(&self, stacks: I) -> String {
+        let mut folded = String::new();
+        for (stack, exclusive) in stacks {
+            let weight = self.weight.of(exclusive);
+            if weight > 0 {
+                folded.push_str(&format!("{} {}\n", stack, weight));
+            }
+        }
+        folded
+    }
+}
+
+impl Default for FoldedStacks {
+    fn default() -> Self {
+        FoldedStacks::new()
+    }
+}
+
+/// Joins frames into a stack, replacing characters that would break it.
+fn stack(frames: &[String]) -> String {
+    let frames: Vec<String> = frames.iter().map(|frame| frame.replace(';', ":").replace(['\n', '\r'], " ")).collect();
+    frames.join(";")
+}
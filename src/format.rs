@@ -0,0 +1,325 @@
+use std::time::Duration;
+
+/// Units a [`DurationFormatter`] can print, from smallest to largest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Unit {
+    Nanoseconds,
+    Microseconds,
+    Milliseconds,
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+}
+
+impl Unit {
+    const ALL: [Unit; 8] = [
+        Unit::Nanoseconds,
+        Unit::Microseconds,
+        Unit::Milliseconds,
+        Unit::Seconds,
+        Unit::Minutes,
+        Unit::Hours,
+        Unit::Days,
+        Unit::Weeks,
+    ];
+
+    /// Length of one unit in nanoseconds.
+    pub fn nanos(self) -> u128 {
+        match self {
+            Unit::Nanoseconds => 1,
+            Unit::Microseconds => 1_000,
+            Unit::Milliseconds => 1_000_000,
+            Unit::Seconds => 1_000_000_000,
+            Unit::Minutes => 60 * 1_000_000_000,
+            Unit::Hours => 60 * 60 * 1_000_000_000,
+            Unit::Days => 24 * 60 * 60 * 1_000_000_000,
+            Unit::Weeks => 7 * 24 * 60 * 60 * 1_000_000_000,
+        }
+    }
+
+    /// Symbol of the unit, with `us` for microseconds.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Unit::Nanoseconds => "ns",
+            Unit::Microseconds => "us",
+            Unit::Milliseconds => "ms",
+            Unit::Seconds => "s",
+            Unit::Minutes => "m",
+            Unit::Hours => "h",
+            Unit::Days => "d",
+            Unit::Weeks => "w",
+        }
+    }
+
+    fn next(self) -> Option<Unit> {
+        Unit::ALL.iter().copied().find(|unit| *unit > self)
+    }
+}
+
+/// How a [`DurationFormatter`] treats digits beyond its precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// Cut off, as [`duration_to_human_string`] does.
+    Truncate,
+    /// Round half up.
+    Nearest,
+    /// Round up.
+    Ceiling,
+}
+
+impl Rounding {
+    fn divide(self, dividend: u128, divisor: u128) -> u128 {
+        match self {
+            Rounding::Truncate => dividend / divisor,
+            Rounding::Nearest => (dividend + divisor / 2) / divisor,
+            Rounding::Ceiling => dividend.div_ceil(divisor),
+        }
+    }
+}
+
+/// Configurable conversion of durations into human readable strings.
+///
+/// By default a duration is printed in the largest unit up to weeks that fits
+/// at least once, truncated to whole units.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{DurationFormatter, Rounding};
+/// use std::time::Duration;
+///
+/// let formatter = DurationFormatter::new().precision(2).rounding(Rounding::Nearest).micro_sign(true);
+/// assert_eq!("13.67µs", formatter.format(&Duration::from_nanos(13674)));
+/// assert_eq!("2.99s", formatter.format(&Duration::from_millis(2990)));
+/// assert_eq!("5.00h", formatter.format(&Duration::from_secs(5 * 3600)));
+/// assert_eq!("1.00ms", formatter.format(&Duration::from_nanos(999_999)));
+/// ```
+///
+/// ## Compound output:
+/// ```
+/// use yatl::{DurationFormatter, Rounding};
+/// use std::time::Duration;
+///
+/// let formatter = DurationFormatter::new().compound(true).precision(1).rounding(Rounding::Nearest);
+/// assert_eq!("1h 02m 03.4s", formatter.format(&Duration::from_millis(3_723_400)));
+/// assert_eq!("2w 3d 00h 00m 00.0s", formatter.format(&Duration::from_secs(17 * 86_400)));
+/// assert_eq!("12.3ms", formatter.format(&Duration::from_micros(12_345)));
+/// ```
+///
+/// ## Aligned for tables:
+/// ```
+/// use yatl::DurationFormatter;
+/// use std::time::Duration;
+///
+/// let formatter = DurationFormatter::new().precision(1).aligned(8);
+/// assert_eq!("  13.6us", formatter.format(&Duration::from_nanos(13674)));
+/// assert_eq!("   2.7s ", formatter.format(&Duration::from_millis(2746)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DurationFormatter {
+    precision: usize,
+    rounding: Rounding,
+    largest_unit: Unit,
+    compound: bool,
+    micro_sign: bool,
+    width: Option<usize>,
+}
+
+impl DurationFormatter {
+    pub fn new() -> Self {
+        DurationFormatter {
+            precision: 0,
+            rounding: Rounding::Truncate,
+            largest_unit: Unit::Weeks,
+            compound: false,
+            micro_sign: false,
+            width: None,
+        }
+    }
+
+    /// The format of [`duration_to_human_string`]: whole units, truncated,
+    /// with minutes as the largest unit.
+    pub fn legacy() -> Self {
+        DurationFormatter::new().largest_unit(Unit::Minutes)
+    }
+
+    /// Number of decimal places, at most 9. In compound mode the decimals
+    /// belong to the seconds.
+    pub fn precision(mut self, precision: usize) -> Self {
+        self.precision = precision.min(9);
+        self
+    }
+
+    pub fn rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Largest unit to use. Larger durations are printed as a multiple of it.
+    pub fn largest_unit(mut self, unit: Unit) -> Self {
+        self.largest_unit = unit;
+        self
+    }
+
+    /// Prints durations of at least one second as a sequence of units down to
+    /// seconds, e.g. `1h 02m 03s`.
+    pub fn compound(mut self, compound: bool) -> Self {
+        self.compound = compound;
+        self
+    }
+
+    /// Uses `µs` instead of `us` for microseconds.
+    pub fn micro_sign(mut self, micro_sign: bool) -> Self {
+        self.micro_sign = micro_sign;
+        self
+    }
+
+    /// Right-aligns the output to `width` characters. Single unit symbols are
+    /// padded to two characters so the numbers line up in a column.
+    pub fn aligned(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    pub fn format(&self, duration: &Duration) -> String {
+        let nanos = duration.as_nanos();
+        let formatted = if self.compound && self.largest_unit > Unit::Seconds {
+            self.format_compound(nanos)
+        } else {
+            None
+        };
+        let formatted = formatted.unwrap_or_else(|| self.format_single(nanos));
+        match self.width {
+            Some(width) => format!("{:>width$}", formatted, width = width),
+            None => formatted,
+        }
+    }
+
+    fn format_single(&self, nanos: u128) -> String {
+        let scale = 10u128.pow(self.precision as u32);
+        let mut unit = Unit::ALL.iter().copied()
+            .filter(|unit| *unit <= self.largest_unit)
+            .rev()
+            .find(|unit| nanos >= unit.nanos())
+            .unwrap_or(Unit::Nanoseconds);
+        let mut scaled = self.rounding.divide(nanos * scale, unit.nanos());
+        // Rounding up may reach the next unit, e.g. 999.999us -> 1000.00us.
+        while let Some(next) = unit.next().filter(|next| *next <= self.largest_unit) {
+            if scaled < next.nanos() / unit.nanos() * scale {
+                break;
+            }
+            unit = next;
+            scaled = self.rounding.divide(nanos * scale, unit.nanos());
+        }
+        let symbol = self.symbol(unit);
+        let symbol = if self.width.is_some() {
+            format!("{:<2}", symbol)
+        } else {
+            symbol.to_string()
+        };
+        format!("{}{}", self.decimal(scaled), symbol)
+    }
+
+    fn format_compound(&self, nanos: u128) -> Option<String> {
+        let granularity = 10u128.pow(9 - self.precision as u32);
+        let mut rest = self.rounding.divide(nanos, granularity) * granularity;
+        if rest < Unit::Seconds.nanos() {
+            return None;
+        }
+        let mut parts = vec![];
+        for unit in Unit::ALL.iter().copied().rev().filter(|unit| *unit <= self.largest_unit && *unit > Unit::Seconds) {
+            let count = rest / unit.nanos();
+            rest %= unit.nanos();
+            if count == 0 && parts.is_empty() {
+                continue;
+            }
+            let digits = if parts.is_empty() { 1 } else { compound_digits(unit) };
+            parts.push(format!("{:0digits$}{}", count, self.symbol(unit), digits = digits));
+        }
+        let seconds = self.decimal(rest / granularity);
+        let digits = if parts.is_empty() { 1 } else if self.precision > 0 { self.precision + 3 } else { 2 };
+        parts.push(format!("{:0>digits$}s", seconds, digits = digits));
+        Some(parts.join(" "))
+    }
+
+    /// Formats `scaled`, which holds `precision` decimal places.
+    fn decimal(&self, scaled: u128) -> String {
+        if self.precision == 0 {
+            return scaled.to_string();
+        }
+        let scale = 10u128.pow(self.precision as u32);
+        format!("{}.{:0precision$}", scaled / scale, scaled % scale, precision = self.precision)
+    }
+
+    fn symbol(&self, unit: Unit) -> &'static str {
+        if unit == Unit::Microseconds && self.micro_sign {
+            "µs"
+        } else {
+            unit.symbol()
+        }
+    }
+}
+
+impl Default for DurationFormatter {
+    fn default() -> Self {
+        DurationFormatter::new()
+    }
+}
+
+/// Digits of a unit that follows a larger one in compound output.
+fn compound_digits(unit: Unit) -> usize {
+    match unit {
+        Unit::Days => 1,
+        _ => 2,
+    }
+}
+
+/// Formats a duration in its largest unit up to minutes, truncating the rest.
+/// This is [`DurationFormatter::legacy`].
+///
+/// # Examples:
+///
+/// ## Formatted as nano seconds:
+/// ```
+/// use yatl::duration_to_human_string;
+/// use std::time::Duration;
+///
+/// assert_eq!("12ns", duration_to_human_string(&Duration::from_nanos(12)));
+/// ```
+///
+/// ## Formatted as micro seconds:
+/// ```
+/// use yatl::duration_to_human_string;
+/// use std::time::Duration;
+///
+/// assert_eq!("13us", duration_to_human_string(&Duration::from_nanos(13674)));
+/// ```
+///
+/// ## Formatted as milli seconds:
+/// ```
+/// use yatl::duration_to_human_string;
+/// use std::time::Duration;
+///
+/// assert_eq!("45ms", duration_to_human_string(&Duration::from_nanos(45674432)));
+/// ```
+///
+/// ## Formatted as seconds:
+/// ```
+/// use yatl::duration_to_human_string;
+/// use std::time::Duration;
+///
+/// assert_eq!("2s", duration_to_human_string(&Duration::from_nanos(2746859738)));
+/// ```
+///
+/// ## Formatted as minutes:
+/// ```
+/// use yatl::duration_to_human_string;
+/// use std::time::Duration;
+///
+/// assert_eq!("13m", duration_to_human_string(&Duration::from_nanos(780897563728)));
+/// ```
+pub fn duration_to_human_string(duration: &Duration) -> String {
+    DurationFormatter::legacy().format(duration)
+}
@@ -0,0 +1,227 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::{Clock, MonotonicClock, SpanContext};
+
+/// Adds [`timed`](FutureExt::timed) and span contexts to every [`Future`].
+pub trait FutureExt: Future + Sized {
+    /// Measures this future, which then resolves to its output together with
+    /// a [`PollReport`].
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{FutureExt, ManualClock};
+    /// use std::future::Future;
+    /// use std::pin::{pin, Pin};
+    /// use std::sync::Arc;
+    /// use std::task::{Context, Poll, Wake, Waker};
+    /// use std::time::Duration;
+    ///
+    /// struct Noop;
+    ///
+    /// impl Wake for Noop {
+    ///     fn wake(self: Arc<Self>) {}
+    /// }
+    ///
+    /// /// Works for 2ms in every poll and is ready after the third one.
+    /// struct Work(ManualClock, u32);
+    ///
+    /// impl Future for Work {
+    ///     type Output = &'static str;
+    ///
+    ///     fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+    ///         self.0.advance(Duration::from_millis(2));
+    ///         self.1 += 1;
+    ///         if self.1 == 3 { Poll::Ready("done") } else { Poll::Pending }
+    ///     }
+    /// }
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut future = pin!(Work(clock.clone(), 0).timed_with(clock.clone()));
+    /// let waker = Waker::from(Arc::new(Noop));
+    /// let mut context = Context::from_waker(&waker);
+    /// let (output, report) = loop {
+    ///     if let Poll::Ready(ready) = future.as_mut().poll(&mut context) {
+    ///         break ready;
+    ///     }
+    ///     // Waiting between the polls.
+    ///     clock.advance(Duration::from_millis(10));
+    /// };
+    ///
+    /// assert_eq!("done", output);
+    /// assert_eq!(3, report.polls());
+    /// assert_eq!(Duration::from_millis(26), report.wall());
+    /// assert_eq!(Duration::from_millis(6), report.busy());
+    /// assert_eq!(Duration::from_millis(20), report.idle());
+    /// assert_eq!(Duration::from_millis(2), report.longest_poll());
+    /// ```
+    fn timed(self) -> TimedFuture<Self> {
+        self.timed_with(MonotonicClock)
+    }
+
+    /// Like [`timed`](FutureExt::timed), measuring with `clock`.
+    fn timed_with<C: Clock>(self, clock: C) -> TimedFuture<Self, C> {
+        TimedFuture {
+            future: Box::pin(self),
+            clock,
+            first_poll: None,
+            report: PollReport::default(),
+        }
+    }
+
+    /// Polls this future in a copy of the spans open right now, see
+    /// [`SpanContext::current`]. Spans opened by the future become children of
+    /// them, whichever thread polls it.
+    ///
+    /// Wrap the futures of spawned tasks in this. The minimal executor of the
+    /// `executor` feature does so already in `spawn`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// # #[cfg(feature = "executor")]
+    /// # fn main() {
+    /// use yatl::{block_on, spawn, yield_now, FutureExt, Profiler};
+    ///
+    /// let profiler = Profiler::new();
+    /// let task = profiler.clone();
+    /// block_on(async move {
+    ///     let _request = task.span("request");
+    ///     let subtasks: Vec<_> = (0..4).map(|_| {
+    ///         let task = task.clone();
+    ///         spawn(async move {
+    ///             let _query = task.span("query");
+    ///             for _ in 0..10 {
+    ///                 // Resumes on any of the worker threads.
+    ///                 yield_now().await;
+    ///             }
+    ///             let _parse = task.span("parse");
+    ///             yield_now().await;
+    ///         })
+    ///     }).collect();
+    ///     for subtask in subtasks {
+    ///         subtask.await.unwrap();
+    ///     }
+    ///     let _render = task.span("render");
+    ///     yield_now().in_current_span().await;
+    /// });
+    ///
+    /// let mut nodes = vec![];
+    /// profiler.tree().walk(|node, depth| nodes.push((depth, node.name().to_string(), node.calls())));
+    /// assert_eq!(
+    ///     vec![
+    ///         (0, "request".to_string(), 1),
+    ///         (1, "query".to_string(), 4),
+    ///         (2, "parse".to_string(), 4),
+    ///         (1, "render".to_string(), 1),
+    ///     ],
+    ///     nodes
+    /// );
+    /// # }
+    /// # #[cfg(not(feature = "executor"))]
+    /// # fn main() {}
+    /// ```
+    fn in_current_span(self) -> InSpanContext<Self> {
+        self.with_span_context(SpanContext::current())
+    }
+
+    /// Polls this future in `context`.
+    fn with_span_context(self, context: SpanContext) -> InSpanContext<Self> {
+        InSpanContext {
+            future: Box::pin(self),
+            context,
+        }
+    }
+}
+
+impl<F: Future> FutureExt for F {}
+
+/// How long a future took, see [`FutureExt::timed`].
+///
+/// A long [`longest_poll`](PollReport::longest_poll) usually means blocking
+/// code is running on the executor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PollReport {
+    wall: Duration,
+    busy: Duration,
+    polls: u64,
+    longest_poll: Duration,
+}
+
+impl PollReport {
+    /// Time from the start of the first poll to the end of the last one.
+    pub fn wall(&self) -> Duration {
+        self.wall
+    }
+
+    /// Time spent inside `poll`.
+    pub fn busy(&self) -> Duration {
+        self.busy
+    }
+
+    /// Time spent waiting between polls.
+    pub fn idle(&self) -> Duration {
+        self.wall.saturating_sub(self.busy)
+    }
+
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    pub fn longest_poll(&self) -> Duration {
+        self.longest_poll
+    }
+}
+
+/// Future returned by [`FutureExt::timed`].
+#[must_use = "futures do nothing unless polled"]
+pub struct TimedFuture<F: Future, C: Clock = MonotonicClock> {
+    future: Pin<Box<F>>,
+    clock: C,
+    first_poll: Option<C::Instant>,
+    report: PollReport,
+}
+
+// The inner future is pinned in its own box, nothing else needs pinning.
+impl<F: Future, C: Clock> Unpin for TimedFuture<F, C> {}
+
+impl<F: Future, C: Clock> Future for TimedFuture<F, C> {
+    type Output = (F::Output, PollReport);
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let started = this.clock.now();
+        let first_poll = *this.first_poll.get_or_insert(started);
+        let poll = this.future.as_mut().poll(context);
+        let finished = this.clock.now();
+
+        let elapsed = this.clock.duration_between(started, finished).unwrap_or_default();
+        let report = &mut this.report;
+        report.polls += 1;
+        report.busy += elapsed;
+        report.longest_poll = report.longest_poll.max(elapsed);
+        report.wall = this.clock.duration_between(first_poll, finished).unwrap_or_default();
+        poll.map(|output| (output, *report))
+    }
+}
+
+/// Future returned by [`FutureExt::in_current_span`] and
+/// [`FutureExt::with_span_context`].
+#[must_use = "futures do nothing unless polled"]
+pub struct InSpanContext<F: Future> {
+    future: Pin<Box<F>>,
+    context: SpanContext,
+}
+
+impl<F: Future> Future for InSpanContext<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let future = &mut this.future;
+        this.context.enter(|| future.as_mut().poll(context))
+    }
+}
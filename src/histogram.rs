@@ -0,0 +1,391 @@
+use std::time::Duration;
+
+use crate::{Error, Result};
+
+/// A fixed-memory, log-linear histogram of durations in the style of
+/// HdrHistogram.
+///
+/// Values between `lowest` and `highest` are recorded with a relative error of
+/// at most one unit in the configured number of significant decimal digits.
+/// Values above `highest` are counted as `highest`. Memory use depends only on
+/// the configuration, not on the number of recorded values.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::Histogram;
+/// use std::time::Duration;
+///
+/// let mut histogram = Histogram::new(Duration::from_nanos(1), Duration::from_secs(60), 3).unwrap();
+/// for micros in 1..=1000 {
+///     histogram.record(Duration::from_micros(micros));
+/// }
+/// assert_eq!(1000, histogram.count());
+/// assert_eq!(Duration::from_micros(1), histogram.min());
+/// assert_eq!(Duration::from_micros(1000), histogram.max());
+///
+/// let p99 = histogram.value_at_quantile(0.99);
+/// assert_eq!(true, p99 >= Duration::from_micros(990) && p99 <= Duration::from_nanos(990_990));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    lowest: u64,
+    highest: u64,
+    significant_digits: u8,
+    unit_magnitude: u32,
+    sub_bucket_half_count_magnitude: u32,
+    sub_bucket_count: u64,
+    sub_bucket_half_count: u64,
+    sub_bucket_mask: u64,
+    leading_zero_count_base: u32,
+    counts: Vec<u64>,
+    total_count: u64,
+    total_nanos: u128,
+    min: u64,
+    max: u64,
+}
+
+/// A range of values and the number of recorded values within it, as returned
+/// by [`Histogram::buckets`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bucket {
+    low: Duration,
+    high: Duration,
+    count: u64,
+}
+
+impl Bucket {
+    /// Lowest value counted in this bucket.
+    pub fn low(&self) -> Duration {
+        self.low
+    }
+
+    /// Highest value counted in this bucket.
+    pub fn high(&self) -> Duration {
+        self.high
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl Histogram {
+    /// Creates a histogram tracking values from `lowest` to `highest` with
+    /// `significant_digits` (1 to 5) significant decimal digits.
+    ///
+    /// `lowest` must be at least 1ns and `highest` at least twice `lowest`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{Error, Histogram};
+    /// use std::time::Duration;
+    ///
+    /// assert_eq!(true, Histogram::new(Duration::from_micros(1), Duration::from_secs(10), 2).is_ok());
+    /// assert_eq!(Err(Error::InvalidHistogram), Histogram::new(Duration::from_secs(1), Duration::from_secs(1), 2));
+    /// assert_eq!(Err(Error::InvalidHistogram), Histogram::new(Duration::from_nanos(1), Duration::from_secs(1), 6));
+    /// ```
+    pub fn new(lowest: Duration, highest: Duration, significant_digits: u8) -> Result<Self> {
+        let lowest = saturating_nanos(lowest);
+        let highest = saturating_nanos(highest);
+        if lowest < 1 || highest / 2 < lowest || !(1..=5).contains(&significant_digits) {
+            return Err(Error::InvalidHistogram);
+        }
+
+        let largest_single_unit_value = 2 * 10u64.pow(u32::from(significant_digits));
+        let sub_bucket_count_magnitude = 64 - (largest_single_unit_value - 1).leading_zeros();
+        let sub_bucket_half_count_magnitude = sub_bucket_count_magnitude.max(1) - 1;
+        let unit_magnitude = 63 - lowest.leading_zeros();
+        if unit_magnitude + sub_bucket_half_count_magnitude + 1 > 63 {
+            return Err(Error::InvalidHistogram);
+        }
+        let sub_bucket_count = 1u64 << (sub_bucket_half_count_magnitude + 1);
+        let sub_bucket_half_count = sub_bucket_count / 2;
+        let sub_bucket_mask = (sub_bucket_count - 1) << unit_magnitude;
+
+        let mut smallest_untrackable = sub_bucket_count << unit_magnitude;
+        let mut bucket_count = 1;
+        while smallest_untrackable <= highest {
+            if smallest_untrackable > u64::MAX / 2 {
+                bucket_count += 1;
+                break;
+            }
+            smallest_untrackable <<= 1;
+            bucket_count += 1;
+        }
+        let counts_len = (bucket_count + 1) * sub_bucket_half_count as usize;
+
+        Ok(Histogram {
+            lowest,
+            highest,
+            significant_digits,
+            unit_magnitude,
+            sub_bucket_half_count_magnitude,
+            sub_bucket_count,
+            sub_bucket_half_count,
+            sub_bucket_mask,
+            leading_zero_count_base: 64 - unit_magnitude - sub_bucket_half_count_magnitude - 1,
+            counts: vec![0; counts_len],
+            total_count: 0,
+            total_nanos: 0,
+            min: u64::MAX,
+            max: 0,
+        })
+    }
+
+    pub fn lowest(&self) -> Duration {
+        Duration::from_nanos(self.lowest)
+    }
+
+    pub fn highest(&self) -> Duration {
+        Duration::from_nanos(self.highest)
+    }
+
+    pub fn significant_digits(&self) -> u8 {
+        self.significant_digits
+    }
+
+    pub fn record(&mut self, value: Duration) {
+        self.record_n(value, 1);
+    }
+
+    /// Records `value` `count` times.
+    pub fn record_n(&mut self, value: Duration, count: u64) {
+        let nanos = saturating_nanos(value).min(self.highest);
+        self.record_nanos(nanos, count);
+        self.total_nanos += u128::from(nanos) * u128::from(count);
+    }
+
+    fn record_nanos(&mut self, nanos: u64, count: u64) {
+        if count == 0 {
+            return;
+        }
+        self.count_nanos(nanos, count);
+        self.min = self.min.min(nanos);
+        self.max = self.max.max(nanos);
+    }
+
+    /// Counts `nanos` without updating the minimum and maximum.
+    fn count_nanos(&mut self, nanos: u64, count: u64) {
+        let index = self.index_of(nanos);
+        self.counts[index] += count;
+        self.total_count += count;
+    }
+
+    /// Adds all values recorded in `other` to this histogram. Values of a
+    /// histogram with a different configuration are re-recorded at the
+    /// midpoints of their buckets, at the precision of this one. The minimum,
+    /// maximum and sum stay exact.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::Histogram;
+    /// use std::time::Duration;
+    ///
+    /// let mut first = Histogram::default();
+    /// let mut second = Histogram::default();
+    /// first.record(Duration::from_millis(1));
+    /// second.record(Duration::from_millis(3));
+    /// first.merge(&second);
+    /// assert_eq!(2, first.count());
+    /// assert_eq!(Duration::from_millis(2), first.mean());
+    /// assert_eq!(Duration::from_millis(3), first.max());
+    ///
+    /// let mut coarse = Histogram::new(Duration::from_micros(1), Duration::from_secs(60), 2).unwrap();
+    /// coarse.record(Duration::from_nanos(5));
+    /// first.merge(&coarse);
+    /// assert_eq!(3, first.count());
+    /// assert_eq!(Duration::from_nanos(5), first.min());
+    /// ```
+    pub fn merge(&mut self, other: &Histogram) {
+        if other.total_count == 0 {
+            return;
+        }
+        if self.counts.len() == other.counts.len()
+            && self.unit_magnitude == other.unit_magnitude
+            && self.sub_bucket_half_count_magnitude == other.sub_bucket_half_count_magnitude {
+            for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+                *count += other;
+            }
+            self.total_count += other.total_count;
+        } else {
+            for (index, count) in other.counts.iter().enumerate().filter(|(_, count)| **count > 0) {
+                let low = other.value_from_index(index);
+                let midpoint = low + (other.highest_equivalent(low) - low) / 2;
+                self.count_nanos(midpoint.min(self.highest), *count);
+            }
+        }
+        self.total_nanos += other.total_nanos;
+        self.min = self.min.min(other.min.min(self.highest));
+        self.max = self.max.max(other.max.min(self.highest));
+    }
+
+    /// Removes all recorded values, keeping the configuration.
+    pub fn clear(&mut self) {
+        self.counts.iter_mut().for_each(|count| *count = 0);
+        self.total_count = 0;
+        self.total_nanos = 0;
+        self.min = u64::MAX;
+        self.max = 0;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.total_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total_count == 0
+    }
+
+    /// Exact sum of all recorded values (after clamping to `highest`).
+    pub fn sum(&self) -> Duration {
+        nanos_to_duration(self.total_nanos)
+    }
+
+    /// Smallest recorded value, or zero if the histogram is empty.
+    pub fn min(&self) -> Duration {
+        if self.is_empty() {
+            Duration::default()
+        } else {
+            Duration::from_nanos(self.min)
+        }
+    }
+
+    /// Largest recorded value, or zero if the histogram is empty.
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max)
+    }
+
+    /// Exact mean of all recorded values, or zero if the histogram is empty.
+    pub fn mean(&self) -> Duration {
+        if self.is_empty() {
+            return Duration::default();
+        }
+        nanos_to_duration(self.total_nanos / u128::from(self.total_count))
+    }
+
+    /// Returns the value below which the fraction `quantile` (0.0 to 1.0) of
+    /// all recorded values lie, or zero if the histogram is empty.
+    ///
+    /// Like HdrHistogram, the highest value of the matching bucket is reported
+    /// (capped to the largest recorded value), so the result may exceed the
+    /// recorded value by the histogram's precision.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::Histogram;
+    /// use std::time::Duration;
+    ///
+    /// let mut histogram = Histogram::default();
+    /// histogram.record_n(Duration::from_millis(1), 90);
+    /// histogram.record_n(Duration::from_millis(50), 10);
+    /// let p90 = histogram.value_at_quantile(0.9);
+    /// assert_eq!(true, p90 >= Duration::from_millis(1) && p90 < Duration::from_micros(1001));
+    /// assert_eq!(Duration::from_millis(50), histogram.value_at_quantile(0.99));
+    /// ```
+    pub fn value_at_quantile(&self, quantile: f64) -> Duration {
+        if self.is_empty() {
+            return Duration::default();
+        }
+        let quantile = quantile.clamp(0.0, 1.0);
+        let target = ((quantile * self.total_count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                let value = self.highest_equivalent(self.value_from_index(index));
+                return Duration::from_nanos(value.clamp(self.min, self.max));
+            }
+        }
+        self.max()
+    }
+
+    /// Iterates over all buckets holding at least one value, in ascending
+    /// order.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::Histogram;
+    /// use std::time::Duration;
+    ///
+    /// let mut histogram = Histogram::default();
+    /// histogram.record(Duration::from_nanos(5));
+    /// histogram.record(Duration::from_nanos(5));
+    /// histogram.record(Duration::from_secs(1));
+    ///
+    /// let buckets: Vec<_> = histogram.buckets().collect();
+    /// assert_eq!(2, buckets.len());
+    /// assert_eq!(Duration::from_nanos(5), buckets[0].low());
+    /// assert_eq!(2, buckets[0].count());
+    /// assert_eq!(true, buckets[1].low() <= Duration::from_secs(1) && Duration::from_secs(1) <= buckets[1].high());
+    /// ```
+    pub fn buckets(&self) -> impl Iterator<Item = Bucket> + '_ {
+        self.counts.iter().enumerate().filter(|(_, count)| **count > 0).map(move |(index, count)| {
+            let low = self.value_from_index(index);
+            Bucket {
+                low: Duration::from_nanos(low),
+                high: Duration::from_nanos(self.highest_equivalent(low)),
+                count: *count,
+            }
+        })
+    }
+
+    fn bucket_index(&self, value: u64) -> u32 {
+        self.leading_zero_count_base - (value | self.sub_bucket_mask).leading_zeros()
+    }
+
+    fn sub_bucket_index(&self, value: u64, bucket_index: u32) -> u64 {
+        value >> (bucket_index + self.unit_magnitude)
+    }
+
+    fn index_of(&self, value: u64) -> usize {
+        let bucket_index = self.bucket_index(value);
+        let sub_bucket_index = self.sub_bucket_index(value, bucket_index);
+        let base = (u64::from(bucket_index) + 1) << self.sub_bucket_half_count_magnitude;
+        (base + sub_bucket_index - self.sub_bucket_half_count) as usize
+    }
+
+    fn value_from_index(&self, index: usize) -> u64 {
+        let index = index as u64;
+        let mut bucket_index = (index >> self.sub_bucket_half_count_magnitude) as i64 - 1;
+        let mut sub_bucket_index = (index & (self.sub_bucket_half_count - 1)) + self.sub_bucket_half_count;
+        if bucket_index < 0 {
+            sub_bucket_index -= self.sub_bucket_half_count;
+            bucket_index = 0;
+        }
+        sub_bucket_index << (bucket_index as u32 + self.unit_magnitude)
+    }
+
+    fn highest_equivalent(&self, value: u64) -> u64 {
+        let bucket_index = self.bucket_index(value);
+        let sub_bucket_index = self.sub_bucket_index(value, bucket_index);
+        let adjusted_bucket = if sub_bucket_index >= self.sub_bucket_count {
+            bucket_index + 1
+        } else {
+            bucket_index
+        };
+        let lowest_equivalent = sub_bucket_index << (bucket_index + self.unit_magnitude);
+        let range = 1u64 << (self.unit_magnitude + adjusted_bucket);
+        lowest_equivalent.saturating_add(range - 1)
+    }
+}
+
+impl Default for Histogram {
+    /// A histogram from 1ns to one hour with 3 significant digits.
+    fn default() -> Self {
+        Histogram::new(Duration::from_nanos(1), Duration::from_secs(3600), 3)
+            .expect("Default histogram configuration is valid")
+    }
+}
+
+fn saturating_nanos(duration: Duration) -> u64 {
+    duration.as_nanos().min(u128::from(u64::MAX)) as u64
+}
+
+fn nanos_to_duration(nanos: u128) -> Duration {
+    Duration::new((nanos / 1_000_000_000) as u64, (nanos % 1_000_000_000) as u32)
+}
@@ -0,0 +1,100 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+use crate::Registry;
+
+/// Longest request head read before giving up.
+const MAX_HEAD: u64 = 8192;
+
+/// Longest time a client may take to send its request or receive the answer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+impl Registry {
+    /// Starts a thread answering `GET /metrics` on `address` with the metrics
+    /// of this registry and returns the address it listens on.
+    ///
+    /// Clients asking for `application/openmetrics-text` in their `Accept`
+    /// header get [`render_openmetrics`](Registry::render_openmetrics), all
+    /// others [`render_prometheus`](Registry::render_prometheus). Binding to
+    /// port 0 picks a free port. Connections are answered one after another,
+    /// each client gets at most five seconds to send its request and to
+    /// receive the answer.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use std::io::{Read, Write};
+    /// use std::net::TcpStream;
+    /// use std::time::Duration;
+    ///
+    /// yatl::global("http.example").record(Duration::from_millis(3));
+    /// let address = yatl::registry().serve("127.0.0.1:0").unwrap();
+    ///
+    /// let mut stream = TcpStream::connect(address).unwrap();
+    /// stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    /// let mut response = String::new();
+    /// stream.read_to_string(&mut response).unwrap();
+    /// assert_eq!(true, response.starts_with("HTTP/1.1 200 OK\r\n"));
+    /// assert_eq!(true, response.contains("\nhttp_example_seconds_count 1\n"));
+    /// ```
+    pub fn serve<A: ToSocketAddrs>(&'static self, address: A) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        thread::Builder::new()
+            .name("yatl-metrics".to_string())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    // A failing or stalling client must not stop the server.
+                    if stream.set_read_timeout(Some(TIMEOUT)).is_ok() && stream.set_write_timeout(Some(TIMEOUT)).is_ok() {
+                        let _ = self.respond(stream);
+                    }
+                }
+            })?;
+        Ok(address)
+    }
+
+    /// Reads a single HTTP request from `stream` and answers it as described
+    /// for [`serve`](Registry::serve), for use with an own listener.
+    pub fn respond<S: Read + Write>(&self, mut stream: S) -> io::Result<()> {
+        let mut request_line = String::new();
+        let mut openmetrics = false;
+        {
+            let mut reader = BufReader::new(Read::take(&mut stream, MAX_HEAD));
+            reader.read_line(&mut request_line)?;
+            let mut header = String::new();
+            loop {
+                header.clear();
+                if reader.read_line(&mut header)? == 0 || header.trim_end().is_empty() {
+                    break;
+                }
+                let lowercase = header.to_ascii_lowercase();
+                if lowercase.starts_with("accept:") && lowercase.contains("application/openmetrics-text") {
+                    openmetrics = true;
+                }
+            }
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let (status, content_type, body) = match (parts.next(), parts.next()) {
+            (Some("GET"), Some("/metrics")) if openmetrics => (
+                "200 OK",
+                "application/openmetrics-text; version=1.0.0; charset=utf-8",
+                self.render_openmetrics(),
+            ),
+            (Some("GET"), Some("/metrics")) => ("200 OK", "text/plain; version=0.0.4; charset=utf-8", self.render_prometheus()),
+            (Some("GET"), _) => ("404 Not Found", "text/plain; charset=utf-8", "Not found\n".to_string()),
+            _ => ("405 Method Not Allowed", "text/plain; charset=utf-8", "Method not allowed\n".to_string()),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body,
+        )?;
+        stream.flush()
+    }
+}
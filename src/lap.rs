@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+/// A single lap of a [`Timer`](crate::Timer).
+///
+/// `I` is the instant type of the timer's [`Clock`](crate::Clock).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LapRecord<I> {
+    pub(crate) index: usize,
+    pub(crate) time: Duration,
+    pub(crate) split: Duration,
+    pub(crate) timestamp: I,
+    pub(crate) name: Option<String>,
+    pub(crate) tags: Vec<(String, String)>,
+}
+
+impl<I: Copy> LapRecord<I> {
+    /// Position of the lap, starting at 0.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Running time of the timer when the lap was taken.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Running time since the previous lap, or since the start for the first
+    /// lap.
+    pub fn split(&self) -> Duration {
+        self.split
+    }
+
+    /// Point in time at which the lap was taken, as reported by the clock.
+    pub fn timestamp(&self) -> I {
+        self.timestamp
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// All key/value attributes in the order they were attached.
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Returns the value of the first attribute with the given key.
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+}
+
+/// Label and attributes attached to a lap by
+/// [`Timer::lap_with`](crate::Timer::lap_with).
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{ManualClock, Timer};
+///
+/// let mut timer = Timer::with_clock(ManualClock::new());
+/// timer.start();
+/// timer.lap_with(|meta| meta.name("load").tag("rows", 1200).tag("table", "users"));
+/// let lap = &timer.lap_records()[0];
+/// assert_eq!(Some("load"), lap.name());
+/// assert_eq!(Some("1200"), lap.tag("rows"));
+/// assert_eq!(Some("users"), lap.tag("table"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LapMeta {
+    pub(crate) name: Option<String>,
+    pub(crate) tags: Vec<(String, String)>,
+}
+
+impl LapMeta {
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn tag<K: Into<String>, V: ToString>(mut self, key: K, value: V) -> Self {
+        self.tags.push((key.into(), value.to_string()));
+        self
+    }
+}
+
+/// Summary of all laps sharing the same name, as returned by
+/// [`Timer::aggregate_laps`](crate::Timer::aggregate_laps). All times are
+/// based on the split of each lap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LapAggregate {
+    pub(crate) name: String,
+    pub(crate) count: usize,
+    pub(crate) total: Duration,
+    pub(crate) min: Duration,
+    pub(crate) max: Duration,
+}
+
+impl LapAggregate {
+    pub(crate) fn new(name: &str, split: Duration) -> Self {
+        LapAggregate {
+            name: name.to_string(),
+            count: 1,
+            total: split,
+            min: split,
+            max: split,
+        }
+    }
+
+    pub(crate) fn add(&mut self, split: Duration) {
+        self.count += 1;
+        self.total += split;
+        self.min = self.min.min(split);
+        self.max = self.max.max(split);
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    pub fn min(&self) -> Duration {
+        self.min
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    pub fn mean(&self) -> Duration {
+        self.total / self.count as u32
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tracing_core::field::{Field, Visit};
+use tracing_core::span::{Attributes, Id, Record};
+use tracing_core::Subscriber;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::lock::lock;
+use crate::{duration_to_human_string, Clock, Histogram, MonotonicClock};
+
+/// [`Layer`] recording how long `tracing` spans are busy (entered) and idle
+/// (open, but not entered) into histograms.
+///
+/// Spans are grouped by their name and the values of the fields selected with
+/// [`field`](TimingLayer::field). The recorded times are read through the
+/// [`Timings`] handle of the layer.
+///
+/// # Examples:
+///
+/// ```
+/// use tracing_subscriber::layer::SubscriberExt;
+/// use yatl::{ManualClock, TimingLayer};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// let layer = TimingLayer::with_clock(clock.clone()).field("method");
+/// let timings = layer.timings();
+///
+/// tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+///     for (method, id) in [("GET", 1), ("GET", 2), ("POST", 3)] {
+///         let span = tracing::info_span!("request", method, id);
+///         clock.advance(Duration::from_millis(1));
+///         let _entered = span.enter();
+///         clock.advance(Duration::from_millis(3));
+///     }
+/// });
+///
+/// let spans = timings.spans();
+/// assert_eq!("request{method=GET}", spans[0].to_string());
+/// assert_eq!(2, spans[0].busy().count());
+/// assert_eq!(Duration::from_millis(6), spans[0].busy().sum());
+/// assert_eq!(Duration::from_millis(2), spans[0].idle().sum());
+/// assert_eq!("request{method=POST}", spans[1].to_string());
+/// ```
+pub struct TimingLayer<C: Clock = MonotonicClock> {
+    clock: C,
+    fields: Vec<String>,
+    timings: Timings,
+    summary_on_drop: bool,
+}
+
+impl TimingLayer {
+    pub fn new() -> Self {
+        TimingLayer::with_clock(MonotonicClock)
+    }
+}
+
+impl Default for TimingLayer {
+    fn default() -> Self {
+        TimingLayer::new()
+    }
+}
+
+impl<C: Clock> TimingLayer<C> {
+    pub fn with_clock(clock: C) -> Self {
+        TimingLayer {
+            clock,
+            fields: vec![],
+            timings: Timings::default(),
+            summary_on_drop: false,
+        }
+    }
+
+    /// Groups spans by the value of the field `name` as well. Spans without
+    /// the field are grouped together.
+    pub fn field<S: Into<String>>(mut self, name: S) -> Self {
+        self.fields.push(name.into());
+        self
+    }
+
+    /// Prints the [summary](Timings::summary) to stderr when the layer is
+    /// dropped together with its subscriber.
+    pub fn summary_on_drop(mut self, summary_on_drop: bool) -> Self {
+        self.summary_on_drop = summary_on_drop;
+        self
+    }
+
+    /// Handle to the times recorded by this layer.
+    pub fn timings(&self) -> Timings {
+        self.timings.clone()
+    }
+
+    fn since(&self, earlier: C::Instant) -> Duration {
+        self.clock.duration_between(earlier, self.clock.now()).unwrap_or_default()
+    }
+}
+
+impl<C: Clock> Drop for TimingLayer<C> {
+    fn drop(&mut self) {
+        if self.summary_on_drop {
+            eprint!("{}", self.timings.summary());
+        }
+    }
+}
+
+/// Times of an open span, kept in its extensions.
+struct SpanState<I> {
+    fields: Vec<(String, String)>,
+    busy: Duration,
+    idle: Duration,
+    /// When the span was last entered or exited.
+    last: I,
+    entered: usize,
+}
+
+impl<S, C> Layer<S> for TimingLayer<C>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    C: Clock + Send + Sync + 'static,
+    C::Instant: Send + Sync + 'static,
+{
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, context: Context<'_, S>) {
+        let span = match context.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut visitor = FieldVisitor {
+            selected: &self.fields,
+            values: vec![],
+        };
+        attributes.record(&mut visitor);
+        span.extensions_mut().insert(SpanState {
+            fields: visitor.values,
+            busy: Duration::default(),
+            idle: Duration::default(),
+            last: self.clock.now(),
+            entered: 0,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, context: Context<'_, S>) {
+        if let Some(span) = context.span(id) {
+            if let Some(state) = span.extensions_mut().get_mut::<SpanState<C::Instant>>() {
+                let mut visitor = FieldVisitor {
+                    selected: &self.fields,
+                    values: std::mem::take(&mut state.fields),
+                };
+                values.record(&mut visitor);
+                state.fields = visitor.values;
+            }
+        }
+    }
+
+    fn on_enter(&self, id: &Id, context: Context<'_, S>) {
+        if let Some(span) = context.span(id) {
+            if let Some(state) = span.extensions_mut().get_mut::<SpanState<C::Instant>>() {
+                if state.entered == 0 {
+                    state.idle += self.since(state.last);
+                    state.last = self.clock.now();
+                }
+                state.entered += 1;
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &Id, context: Context<'_, S>) {
+        if let Some(span) = context.span(id) {
+            if let Some(state) = span.extensions_mut().get_mut::<SpanState<C::Instant>>() {
+                state.entered = state.entered.saturating_sub(1);
+                if state.entered == 0 {
+                    state.busy += self.since(state.last);
+                    state.last = self.clock.now();
+                }
+            }
+        }
+    }
+
+    fn on_close(&self, id: Id, context: Context<'_, S>) {
+        let span = match context.span(&id) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut extensions = span.extensions_mut();
+        let mut state = match extensions.remove::<SpanState<C::Instant>>() {
+            Some(state) => state,
+            None => return,
+        };
+        if state.entered == 0 {
+            state.idle += self.since(state.last);
+        }
+        let mut fields = state.fields;
+        fields.sort_by_key(|(name, _)| self.fields.iter().position(|selected| selected == name));
+        let mut spans = lock(&self.timings.spans);
+        let timings = spans.entry((span.name().to_string(), fields.clone())).or_insert_with(|| SpanTimings {
+            name: span.name().to_string(),
+            fields,
+            busy: Histogram::default(),
+            idle: Histogram::default(),
+        });
+        timings.busy.record(state.busy);
+        timings.idle.record(state.idle);
+    }
+}
+
+/// Collects the values of the selected fields.
+struct FieldVisitor<'a> {
+    selected: &'a [String],
+    values: Vec<(String, String)>,
+}
+
+impl FieldVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: String) {
+        if !self.selected.iter().any(|selected| selected == field.name()) {
+            return;
+        }
+        match self.values.iter_mut().find(|(name, _)| name == field.name()) {
+            Some((_, old)) => *old = value,
+            None => self.values.push((field.name().to_string(), value)),
+        }
+    }
+}
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{:?}", value));
+    }
+}
+
+type SpanKey = (String, Vec<(String, String)>);
+
+/// Handle to the times recorded by a [`TimingLayer`]. Clones share the same
+/// times.
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    spans: Arc<Mutex<BTreeMap<SpanKey, SpanTimings>>>,
+}
+
+impl Timings {
+    /// Times of all closed spans, sorted by name and field values.
+    pub fn spans(&self) -> Vec<SpanTimings> {
+        lock(&self.spans).values().cloned().collect()
+    }
+
+    /// Removes all recorded times.
+    pub fn clear(&self) {
+        lock(&self.spans).clear();
+    }
+
+    /// Renders a table of the recorded spans with the number of calls, the
+    /// mean, median, 99th percentile and maximum busy time and the mean idle
+    /// time.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use tracing_subscriber::layer::SubscriberExt;
+    /// use yatl::{ManualClock, TimingLayer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let layer = TimingLayer::with_clock(clock.clone());
+    /// let timings = layer.timings();
+    /// tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+    ///     let _load = tracing::info_span!("load").entered();
+    ///     clock.advance(Duration::from_millis(12));
+    /// });
+    ///
+    /// assert_eq!(
+    ///     "span  calls  mean  p50   p99   max   idle\n\
+    ///      load      1  12ms  12ms  12ms  12ms  0ns\n",
+    ///     timings.summary()
+    /// );
+    /// ```
+    pub fn summary(&self) -> String {
+        let mut rows = vec![vec![
+            "span".to_string(),
+            "calls".to_string(),
+            "mean".to_string(),
+            "p50".to_string(),
+            "p99".to_string(),
+            "max".to_string(),
+            "idle".to_string(),
+        ]];
+        for span in self.spans() {
+            let busy = &span.busy;
+            rows.push(vec![
+                span.to_string(),
+                busy.count().to_string(),
+                duration_to_human_string(&busy.mean()),
+                duration_to_human_string(&busy.value_at_quantile(0.5)),
+                duration_to_human_string(&busy.value_at_quantile(0.99)),
+                duration_to_human_string(&busy.max()),
+                duration_to_human_string(&span.idle.mean()),
+            ]);
+        }
+        let widths: Vec<usize> = (0..rows[0].len())
+            .map(|column| rows.iter().map(|row| row[column].chars().count()).max().unwrap_or(0))
+            .collect();
+
+        let mut table = String::new();
+        for row in rows {
+            let mut line = String::new();
+            for (column, cell) in row.iter().enumerate() {
+                if column > 0 {
+                    line.push_str("  ");
+                }
+                if column == 1 {
+                    let _ = write!(line, "{:>width$}", cell, width = widths[column]);
+                } else {
+                    let _ = write!(line, "{:<width$}", cell, width = widths[column]);
+                }
+            }
+            table.push_str(line.trim_end());
+            table.push('\n');
+        }
+        table
+    }
+
+    /// Prints the [summary](Timings::summary) to stderr.
+    pub fn print_summary(&self) {
+        eprint!("{}", self.summary());
+    }
+}
+
+/// Busy and idle times of all closed spans with the same name and selected
+/// field values, see [`TimingLayer`].
+///
+/// Displayed as `name{field=value,...}`.
+#[derive(Debug, Clone)]
+pub struct SpanTimings {
+    name: String,
+    fields: Vec<(String, String)>,
+    busy: Histogram,
+    idle: Histogram,
+}
+
+impl SpanTimings {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Values of the selected fields, in the order they were selected.
+    pub fn fields(&self) -> &[(String, String)] {
+        &self.fields
+    }
+
+    /// Time spent inside the spans.
+    pub fn busy(&self) -> &Histogram {
+        &self.busy
+    }
+
+    /// Time the spans were open without being entered.
+    pub fn idle(&self) -> &Histogram {
+        &self.idle
+    }
+}
+
+impl fmt::Display for SpanTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.fields.is_empty() {
+            let fields: Vec<String> = self.fields.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+            write!(f, "{{{}}}", fields.join(","))?;
+        }
+        Ok(())
+    }
+}
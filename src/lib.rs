@@ -1,18 +1,142 @@
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 
-pub struct Timer {
-    started: Option<SystemTime>,
-    laps: Vec<Duration>,
+mod clock;
+mod error;
+#[cfg(feature = "executor")]
+mod executor;
+mod exposition;
+#[cfg(feature = "svg")]
+mod flamegraph;
+mod folded;
+mod format;
+mod future;
+mod histogram;
+#[cfg(feature = "http")]
+mod http;
+mod lap;
+#[cfg(feature = "tracing")]
+mod layer;
+mod lock;
+mod macros;
+mod measure;
+mod profiler;
+mod registry;
+mod scope;
+mod sink;
+#[cfg(feature = "log")]
+mod slow;
+mod sync;
+mod trace;
+mod stats;
+
+pub use clock::{Clock, ManualClock, MonotonicClock, SystemClock};
+#[cfg(target_os = "linux")]
+pub use clock::{BootTimeClock, MonotonicCoarseClock, MonotonicRawClock};
+pub use error::{Error, Result};
+#[cfg(feature = "executor")]
+pub use executor::{block_on, spawn, yield_now, JoinHandle};
+#[cfg(feature = "svg")]
+pub use flamegraph::{FlameGraph, Orientation};
+pub use folded::{FoldedStacks, Weight};
+pub use format::{duration_to_human_string, DurationFormatter, Rounding, Unit};
+pub use future::{FutureExt, InSpanContext, PollReport, TimedFuture};
+pub use histogram::{Bucket, Histogram};
+pub use lap::{LapAggregate, LapMeta, LapRecord};
+#[cfg(feature = "tracing")]
+pub use layer::{SpanTimings, TimingLayer, Timings};
+pub use measure::{measure, measure_catch, measure_n, measure_results, Outcomes};
+pub use profiler::{CallTree, Node, Profiler, Span, SpanContext, SpanRecord};
+pub use registry::{global, registry, Registry};
+pub use scope::Scope;
+pub use sink::{Sink, StderrSink, Timed};
+#[cfg(feature = "log")]
+pub use slow::{SlowLogger, SlowOperation};
+pub use sync::{flush_local, SyncGuard, SyncTimer};
+pub use trace::TraceWriter;
+#[cfg(feature = "macros")]
+pub use yatl_macros::timed;
+pub use yatl_parse::{parse_duration, ParseError, ParseErrorKind};
+pub use stats::Stats;
+
+pub struct Timer<C: Clock = MonotonicClock> {
+    clock: C,
+    started: Option<C::Instant>,
+    paused: Option<C::Instant>,
+    pauses: Vec<Pause>,
+    stopped: Option<Duration>,
+    laps: Vec<LapRecord<C::Instant>>,
+    last_lap: Duration,
+    histogram: Option<Histogram>,
+}
+
+/// The lifecycle state of a [`Timer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Not started yet, or reset.
+    Idle,
+    Running,
+    Paused,
+    /// Stopped with a frozen final elapsed time.
+    Stopped,
+}
+
+/// A finished pause of a [`Timer`], as recorded by [`Timer::resume`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pause {
+    at: Duration,
+    duration: Duration,
+}
+
+impl Pause {
+    /// Running time of the timer when it was paused.
+    pub fn at(&self) -> Duration {
+        self.at
+    }
+
+    /// How long the timer was paused.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
 }
 
 impl Timer {
     pub fn new() -> Self {
+        Timer::with_clock(MonotonicClock)
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Timer::new()
+    }
+}
+
+impl<C: Clock> Timer<C> {
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{SystemClock, Timer};
+    ///
+    /// let mut timer = Timer::with_clock(SystemClock);
+    /// assert_eq!(Ok(()), timer.start());
+    /// ```
+    pub fn with_clock(clock: C) -> Self {
         Timer {
+            clock,
             started: None,
+            paused: None,
+            pauses: vec![],
+            stopped: None,
             laps: vec![],
+            last_lap: Duration::default(),
+            histogram: None,
         }
     }
 
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
     /// # Examples:
     ///
     /// ```
@@ -22,13 +146,13 @@ impl Timer {
     /// assert_eq!(Ok(()), timer.start());
     /// assert_eq!(true, timer.start().is_err());
     /// ```
-    pub fn start(&mut self) -> Result<(), &str> {
+    pub fn start(&mut self) -> Result<()> {
         match self.started {
             None => {
-                self.started = Some(SystemTime::now());
+                self.started = Some(self.clock.now());
                 Ok(())
             }
-            Some(_) => Err("Timer already started!")
+            Some(_) => Err(Error::AlreadyStarted),
         }
     }
 
@@ -42,10 +166,10 @@ impl Timer {
     /// timer.start();
     /// assert_eq!(true, timer.start_time().is_ok());
     /// ```
-    pub fn start_time(&self) -> Result<SystemTime, &str> {
+    pub fn start_time(&self) -> Result<C::Instant> {
         match self.started {
-            Some(s) => Ok(s.clone()),
-            None => Err("Timer not started!")
+            Some(s) => Ok(s),
+            None => Err(Error::NotStarted),
         }
     }
 
@@ -64,20 +188,304 @@ impl Timer {
     /// assert_eq!(true, lap.is_ok());
     /// assert_eq!(true, lap.unwrap().as_nanos() > 0, "No time passed?!");
     /// ```
-    pub fn lap(&mut self) -> Result<Duration, String> {
-        match self.started {
-            Some(s) => {
-                match s.elapsed() {
-                    Ok(e) => {
-                        self.laps.push(e);
-                        Ok(e.clone())
-                    }
-                    Err(e) =>
-                    Err(format!("Internal Error: {:?}", e))
-                }
-            }
-            None => Err("Timer not started!".to_string())
+    ///
+    /// ## Without sleeping, using a [`ManualClock`]:
+    /// ```
+    /// use yatl::{ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// timer.start();
+    /// clock.advance(Duration::from_micros(10));
+    /// assert_eq!(Ok(Duration::from_micros(10)), timer.lap());
+    /// ```
+    pub fn lap(&mut self) -> Result<Duration> {
+        self.lap_with(|meta| meta)
+    }
+
+    /// Records a lap with a label.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// timer.start();
+    /// clock.advance(Duration::from_millis(2));
+    /// timer.lap_named("parse");
+    /// clock.advance(Duration::from_millis(43));
+    /// timer.lap_named("execute");
+    /// assert_eq!(Some(Duration::from_millis(43)), timer.find_lap("execute").map(|lap| lap.split()));
+    /// ```
+    pub fn lap_named<S: Into<String>>(&mut self, name: S) -> Result<Duration> {
+        self.lap_with(|meta| meta.name(name))
+    }
+
+    /// Records a lap with a label and attributes set on the given [`LapMeta`].
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::Timer;
+    ///
+    /// let mut timer = Timer::new();
+    /// timer.start();
+    /// timer.lap_with(|meta| meta.tag("rows", 1200));
+    /// assert_eq!(Some("1200"), timer.lap_records()[0].tag("rows"));
+    /// ```
+    pub fn lap_with<F: FnOnce(LapMeta) -> LapMeta>(&mut self, meta: F) -> Result<Duration> {
+        self.lap_at(|_, lap| meta(lap))
+    }
+
+    /// Like [`lap_with`](Timer::lap_with), but `meta` also gets the running
+    /// time of the lap.
+    pub(crate) fn lap_at<F: FnOnce(Duration, LapMeta) -> LapMeta>(&mut self, meta: F) -> Result<Duration> {
+        self.check_not_stopped()?;
+        let now = self.clock.now();
+        let time = self.elapsed_until(|| now)?;
+        let split = time.saturating_sub(self.last_lap);
+        self.last_lap = time;
+        if let Some(histogram) = &mut self.histogram {
+            histogram.record(split);
+            return Ok(time);
+        }
+        let LapMeta { name, tags } = meta(time, LapMeta::default());
+        self.laps.push(LapRecord {
+            index: self.laps.len(),
+            time,
+            split,
+            timestamp: now,
+            name,
+            tags,
+        });
+        Ok(time)
+    }
+
+    /// Returns the running time of the timer, excluding all pauses.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{Error, ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// assert_eq!(Err(Error::NotStarted), timer.elapsed());
+    /// timer.start();
+    /// clock.advance(Duration::from_millis(5));
+    /// assert_eq!(Ok(Duration::from_millis(5)), timer.elapsed());
+    /// ```
+    pub fn elapsed(&self) -> Result<Duration> {
+        self.elapsed_until(|| self.clock.now())
+    }
+
+    /// Pauses the timer. The time until [`resume`](Timer::resume) is called is
+    /// not counted as running time.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{Error, ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// assert_eq!(Err(Error::NotStarted), timer.pause());
+    /// timer.start();
+    /// clock.advance(Duration::from_millis(2));
+    /// assert_eq!(Ok(()), timer.pause());
+    /// assert_eq!(Err(Error::AlreadyPaused), timer.pause());
+    /// clock.advance(Duration::from_millis(40));
+    /// assert_eq!(Ok(Duration::from_millis(2)), timer.lap());
+    /// ```
+    pub fn pause(&mut self) -> Result<()> {
+        self.check_not_stopped()?;
+        if self.paused.is_some() {
+            return Err(Error::AlreadyPaused);
+        }
+        self.paused = Some(self.clock.now());
+        Ok(())
+    }
+
+    /// Resumes a paused timer and records the pause.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{Error, ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// timer.start();
+    /// assert_eq!(Err(Error::NotPaused), timer.resume());
+    /// clock.advance(Duration::from_millis(2));
+    /// timer.pause();
+    /// clock.advance(Duration::from_millis(40));
+    /// assert_eq!(Ok(()), timer.resume());
+    /// clock.advance(Duration::from_millis(3));
+    /// assert_eq!(Ok(Duration::from_millis(5)), timer.lap());
+    /// assert_eq!(Duration::from_millis(40), timer.paused_time());
+    /// assert_eq!(Duration::from_millis(2), timer.pauses()[0].at());
+    /// ```
+    pub fn resume(&mut self) -> Result<()> {
+        self.check_not_stopped()?;
+        let paused = self.paused.ok_or(Error::NotPaused)?;
+        let at = self.elapsed()?;
+        let duration = self.clock.duration_between(paused, self.clock.now())
+            .map_err(|by| Error::ClockWentBackwards { by })?;
+        self.paused = None;
+        self.pauses.push(Pause { at, duration });
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Stops the timer and freezes its final running time. A paused timer
+    /// gets its ongoing pause recorded first.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{Error, ManualClock, State, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// assert_eq!(Err(Error::NotStarted), timer.stop());
+    /// timer.start();
+    /// clock.advance(Duration::from_millis(7));
+    /// assert_eq!(Ok(Duration::from_millis(7)), timer.stop());
+    /// assert_eq!(State::Stopped, timer.state());
+    /// clock.advance(Duration::from_millis(100));
+    /// assert_eq!(Ok(Duration::from_millis(7)), timer.elapsed());
+    /// assert_eq!(Err(Error::Stopped), timer.lap());
+    /// assert_eq!(Err(Error::AlreadyStarted), timer.start());
+    /// ```
+    pub fn stop(&mut self) -> Result<Duration> {
+        self.check_not_stopped()?;
+        if self.paused.is_some() {
+            self.resume()?;
+        }
+        let elapsed = self.elapsed()?;
+        self.stopped = Some(elapsed);
+        Ok(elapsed)
+    }
+
+    /// Clears all laps, pauses and state, bringing the timer back to
+    /// [`State::Idle`].
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{State, Timer};
+    ///
+    /// let mut timer = Timer::new();
+    /// timer.start();
+    /// timer.lap();
+    /// timer.reset();
+    /// assert_eq!(State::Idle, timer.state());
+    /// assert_eq!(true, timer.laps().is_empty());
+    /// assert_eq!(Ok(()), timer.start());
+    /// ```
+    pub fn reset(&mut self) {
+        self.started = None;
+        self.paused = None;
+        self.pauses.clear();
+        self.stopped = None;
+        self.laps.clear();
+        self.last_lap = Duration::default();
+        if let Some(histogram) = &mut self.histogram {
+            histogram.clear();
+        }
+    }
+
+    /// Resets and starts the timer in one step, returning the running time
+    /// it had before. An idle timer reports a previous total of zero.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, State, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// assert_eq!(Ok(Duration::from_secs(0)), timer.restart());
+    /// clock.advance(Duration::from_millis(12));
+    /// timer.lap();
+    /// assert_eq!(Ok(Duration::from_millis(12)), timer.restart());
+    /// assert_eq!(State::Running, timer.state());
+    /// assert_eq!(true, timer.laps().is_empty());
+    /// ```
+    pub fn restart(&mut self) -> Result<Duration> {
+        let previous = match self.state() {
+            State::Idle => Duration::default(),
+            _ => self.elapsed()?,
+        };
+        self.reset();
+        self.start()?;
+        Ok(previous)
+    }
+
+    pub fn state(&self) -> State {
+        if self.started.is_none() {
+            State::Idle
+        } else if self.stopped.is_some() {
+            State::Stopped
+        } else if self.paused.is_some() {
+            State::Paused
+        } else {
+            State::Running
+        }
+    }
+
+    /// Returns the total time the timer has been paused, including a pause that
+    /// is still ongoing.
+    pub fn paused_time(&self) -> Duration {
+        let ongoing = self.paused
+            .and_then(|paused| self.clock.duration_between(paused, self.clock.now()).ok())
+            .unwrap_or_default();
+        self.finished_pauses() + ongoing
+    }
+
+    /// Returns all finished pauses in the order they happened.
+    pub fn pauses(&self) -> &[Pause] {
+        &self.pauses
+    }
+
+    fn check_not_stopped(&self) -> Result<()> {
+        match self.state() {
+            State::Idle => Err(Error::NotStarted),
+            State::Stopped => Err(Error::Stopped),
+            _ => Ok(()),
+        }
+    }
+
+    /// Running time until `now`, which is only read while the timer runs.
+    fn elapsed_until<F: FnOnce() -> C::Instant>(&self, now: F) -> Result<Duration> {
+        if let Some(stopped) = self.stopped {
+            return Ok(stopped);
         }
+        let started = self.started.ok_or(Error::NotStarted)?;
+        let until = match self.paused {
+            Some(paused) => paused,
+            None => now(),
+        };
+        let wall = self.clock.duration_between(started, until)
+            .map_err(|by| Error::ClockWentBackwards { by })?;
+        Ok(wall.saturating_sub(self.finished_pauses()))
+    }
+
+    fn finished_pauses(&self) -> Duration {
+        self.pauses.iter().map(Pause::duration).sum()
     }
 
     /// # Examples:
@@ -97,66 +505,204 @@ impl Timer {
     /// assert_eq!(laps, timer.laps())
     /// ```
     pub fn laps(&self) -> Vec<Duration> {
-        self.laps.clone()
+        self.laps.iter().map(LapRecord::time).collect()
     }
 
     pub fn laps_formatted(&self) -> Vec<String> {
-        let formatted: Vec<String> = self.laps.iter().map(|d| duration_to_human_string(d)).collect();
+        let formatted: Vec<String> = self.laps().iter().map(duration_to_human_string).collect();
         formatted
     }
-}
 
-/// # Examples:
-///
-/// ## Formatted as nano seconds:
-/// ```
-/// use yatl::duration_to_human_string;
-/// use std::time::Duration;
-///
-/// assert_eq!("12ns", duration_to_human_string(&Duration::from_nanos(12)));
-/// ```
-///
-/// ## Formatted as micro seconds:
-/// ```
-/// use yatl::duration_to_human_string;
-/// use std::time::Duration;
-///
-/// assert_eq!("13us", duration_to_human_string(&Duration::from_nanos(13674)));
-/// ```
-///
-/// ## Formatted as milli seconds:
-/// ```
-/// use yatl::duration_to_human_string;
-/// use std::time::Duration;
-///
-/// assert_eq!("45ms", duration_to_human_string(&Duration::from_nanos(45674432)));
-/// ```
-///
-/// ## Formatted as seconds:
-/// ```
-/// use yatl::duration_to_human_string;
-/// use std::time::Duration;
-///
-/// assert_eq!("2s", duration_to_human_string(&Duration::from_nanos(2746859738)));
-/// ```
-///
-/// ## Formatted as minutes:
-/// ```
-/// use yatl::duration_to_human_string;
-/// use std::time::Duration;
-///
-/// assert_eq!("13m", duration_to_human_string(&Duration::from_nanos(780897563728)));
-/// ```
-pub fn duration_to_human_string(duration: &Duration) -> String {
-    return if duration.as_nanos() < 1000 {
-        format!("{}ns", duration.as_nanos())
-    } else if duration.as_micros() < 1000 {
-        format!("{}us", duration.as_micros())
-    } else if duration.as_millis() < 1000 {
-        format!("{}ms", duration.as_millis())
-    } else if duration.as_secs() < 60 {
-        format!("{}s", duration.as_secs())
-    }else {
-        format!("{}m", duration.as_secs() / 60)
-    }
-}
\ No newline at end of file
+    /// Returns the time between consecutive laps. The first split is the time
+    /// from the start to the first lap.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let mut timer = Timer::with_clock(ManualClock::auto_advancing(Duration::from_millis(2)));
+    /// timer.start();
+    /// timer.lap();
+    /// timer.lap();
+    /// assert_eq!(vec![Duration::from_millis(2), Duration::from_millis(4)], timer.laps());
+    /// assert_eq!(vec![Duration::from_millis(2), Duration::from_millis(2)], timer.splits());
+    /// assert_eq!(vec!["2ms", "2ms"], timer.splits_formatted());
+    /// ```
+    pub fn splits(&self) -> Vec<Duration> {
+        self.laps.iter().map(LapRecord::split).collect()
+    }
+
+    pub fn splits_formatted(&self) -> Vec<String> {
+        self.splits().iter().map(duration_to_human_string).collect()
+    }
+
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// timer.start();
+    /// assert_eq!(None, timer.last_split());
+    /// clock.advance(Duration::from_millis(5));
+    /// timer.lap();
+    /// clock.advance(Duration::from_millis(3));
+    /// timer.lap();
+    /// assert_eq!(Some(Duration::from_millis(3)), timer.last_split());
+    /// ```
+    pub fn last_split(&self) -> Option<Duration> {
+        self.laps.last().map(LapRecord::split)
+    }
+
+    /// Returns all laps with their index, cumulative time, split time and
+    /// timestamp.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// clock.set(Duration::from_secs(100));
+    /// timer.start();
+    /// clock.advance(Duration::from_millis(5));
+    /// timer.lap();
+    /// clock.advance(Duration::from_millis(3));
+    /// timer.lap();
+    ///
+    /// let lap = &timer.lap_records()[1];
+    /// assert_eq!(1, lap.index());
+    /// assert_eq!(Duration::from_millis(8), lap.time());
+    /// assert_eq!(Duration::from_millis(3), lap.split());
+    /// assert_eq!(Duration::from_millis(100_008), lap.timestamp());
+    /// ```
+    pub fn lap_records(&self) -> &[LapRecord<C::Instant>] {
+        &self.laps
+    }
+
+    /// Returns a guard that records a lap named `name` when it is dropped,
+    /// see [`Scope`]. An idle timer is started first.
+    pub fn scope<S: Into<String>>(&mut self, name: S) -> Scope<'_, C> {
+        if self.state() == State::Idle {
+            let _ = self.start();
+        }
+        Scope::new(self, name.into())
+    }
+
+    /// Runs `f` inside a [`scope`](Timer::scope) named `name` and returns its
+    /// result together with the time it took.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// let (rows, elapsed) = timer.measure("query", || {
+    ///     clock.advance(Duration::from_millis(8));
+    ///     1200
+    /// });
+    /// assert_eq!(1200, rows);
+    /// assert_eq!(Duration::from_millis(8), elapsed);
+    /// assert_eq!(Some(Duration::from_millis(8)), timer.last_split());
+    /// ```
+    pub fn measure<S: Into<String>, T, F: FnOnce() -> T>(&mut self, name: S, f: F) -> (T, Duration) {
+        let scope = self.scope(name);
+        let value = f();
+        (value, scope.finish())
+    }
+
+    /// Records the split of every following lap into `histogram` instead of
+    /// keeping a [`LapRecord`] per lap, so memory use stays fixed however many
+    /// laps are taken. Laps recorded so far are kept, names and attributes of
+    /// following laps are dropped.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{Histogram, ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let mut timer = Timer::with_clock(ManualClock::auto_advancing(Duration::from_micros(250)));
+    /// timer.record_into(Histogram::default());
+    /// timer.start();
+    /// for _ in 0..1_000_000 {
+    ///     timer.lap();
+    /// }
+    /// assert_eq!(true, timer.laps().is_empty());
+    /// let histogram = timer.histogram().unwrap();
+    /// assert_eq!(1_000_000, histogram.count());
+    /// assert_eq!(Duration::from_micros(250), histogram.max());
+    /// ```
+    pub fn record_into(&mut self, histogram: Histogram) {
+        self.histogram = Some(histogram);
+    }
+
+    pub fn histogram(&self) -> Option<&Histogram> {
+        self.histogram.as_ref()
+    }
+
+    /// Removes the histogram set by [`record_into`](Timer::record_into), so
+    /// following laps are kept as [`LapRecord`]s again.
+    pub fn take_histogram(&mut self) -> Option<Histogram> {
+        self.histogram.take()
+    }
+
+    /// Returns the first lap with the given name.
+    pub fn find_lap(&self, name: &str) -> Option<&LapRecord<C::Instant>> {
+        self.laps.iter().find(|lap| lap.name() == Some(name))
+    }
+
+    /// Returns all laps with the given name.
+    pub fn laps_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a LapRecord<C::Instant>> + 'a {
+        self.laps.iter().filter(move |lap| lap.name() == Some(name))
+    }
+
+    /// Groups the splits of all named laps by name, in the order each name
+    /// first appeared. Unnamed laps are left out.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// timer.start();
+    /// for rows in &[10, 30] {
+    ///     clock.advance(Duration::from_millis(1));
+    ///     timer.lap_named("parse");
+    ///     clock.advance(Duration::from_millis(*rows));
+    ///     timer.lap_named("execute");
+    /// }
+    ///
+    /// let aggregates = timer.aggregate_laps();
+    /// assert_eq!("parse", aggregates[0].name());
+    /// assert_eq!(2, aggregates[0].count());
+    /// assert_eq!(Duration::from_millis(2), aggregates[0].total());
+    /// assert_eq!(Duration::from_millis(10), aggregates[1].min());
+    /// assert_eq!(Duration::from_millis(30), aggregates[1].max());
+    /// assert_eq!(Duration::from_millis(20), aggregates[1].mean());
+    /// ```
+    pub fn aggregate_laps(&self) -> Vec<LapAggregate> {
+        let mut aggregates: Vec<LapAggregate> = vec![];
+        for lap in &self.laps {
+            if let Some(name) = lap.name() {
+                match aggregates.iter_mut().find(|aggregate| aggregate.name() == name) {
+                    Some(aggregate) => aggregate.add(lap.split()),
+                    None => aggregates.push(LapAggregate::new(name, lap.split())),
+                }
+            }
+        }
+        aggregates
+    }
+}
@@ -0,0 +1,21 @@
+/// Evaluates an expression and returns its value together with the time it
+/// took as `(value, Duration)`.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::time;
+///
+/// let (sum, elapsed) = time!((1..=100).sum::<u32>());
+/// assert_eq!(5050, sum);
+/// assert_eq!(true, elapsed.as_secs() < 60);
+/// ```
+#[macro_export]
+macro_rules! time {
+    ($expr:expr) => {{
+        let mut timer = $crate::Timer::new();
+        let _ = timer.start();
+        let value = $expr;
+        (value, timer.lap().unwrap_or_default())
+    }};
+}
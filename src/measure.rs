@@ -0,0 +1,110 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::thread;
+use std::time::Duration;
+
+use crate::{Stats, Timer};
+
+/// Runs `f` and returns its result together with the time it took.
+///
+/// # Examples:
+///
+/// ```
+/// let (value, elapsed) = yatl::measure(|| 6 * 7);
+/// assert_eq!(42, value);
+/// assert_eq!(true, elapsed.as_secs() < 60);
+/// ```
+pub fn measure<T, F: FnOnce() -> T>(f: F) -> (T, Duration) {
+    let mut timer = Timer::new();
+    let _ = timer.start();
+    let value = f();
+    (value, timer.lap().unwrap_or_default())
+}
+
+/// Like [`measure`], but catches a panic of `f` and returns it as `Err`, so the
+/// time until the panic is not lost.
+///
+/// # Examples:
+///
+/// ```
+/// let (result, _elapsed) = yatl::measure_catch(|| -> u32 { panic!("Failed") });
+/// assert_eq!(true, result.is_err());
+/// ```
+pub fn measure_catch<T, F: FnOnce() -> T>(f: F) -> (thread::Result<T>, Duration) {
+    measure(|| catch_unwind(AssertUnwindSafe(f)))
+}
+
+/// Runs `f` `n` times and summarizes the time of each run.
+///
+/// # Examples:
+///
+/// ```
+/// let stats = yatl::measure_n(100, || (1..=1000).sum::<u64>());
+/// assert_eq!(100, stats.count());
+/// assert_eq!(true, stats.min() <= stats.median());
+/// ```
+pub fn measure_n<T, F: FnMut() -> T>(n: usize, mut f: F) -> Stats {
+    (0..n).map(|_| measure(&mut f).1).collect()
+}
+
+/// Runs a fallible `f` `n` times, summarizing successful, failed and panicked
+/// runs separately.
+///
+/// # Examples:
+///
+/// ```
+/// let mut run = 0;
+/// let outcomes = yatl::measure_results(10, || {
+///     run += 1;
+///     match run % 5 {
+///         0 => panic!("Run {} failed badly", run),
+///         1 | 2 => Err(run),
+///         _ => Ok(run),
+///     }
+/// });
+/// assert_eq!(4, outcomes.ok().count());
+/// assert_eq!(4, outcomes.err().count());
+/// assert_eq!(2, outcomes.panicked().count());
+/// ```
+pub fn measure_results<T, E, F: FnMut() -> Result<T, E>>(n: usize, mut f: F) -> Outcomes {
+    let mut ok = vec![];
+    let mut err = vec![];
+    let mut panicked = vec![];
+    for _ in 0..n {
+        match measure_catch(&mut f) {
+            (Ok(Ok(_)), elapsed) => ok.push(elapsed),
+            (Ok(Err(_)), elapsed) => err.push(elapsed),
+            (Err(_), elapsed) => panicked.push(elapsed),
+        }
+    }
+    Outcomes {
+        ok: Stats::from_durations(ok),
+        err: Stats::from_durations(err),
+        panicked: Stats::from_durations(panicked),
+    }
+}
+
+/// Timings of repeated runs of a fallible closure, as returned by
+/// [`measure_results`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Outcomes {
+    ok: Stats,
+    err: Stats,
+    panicked: Stats,
+}
+
+impl Outcomes {
+    /// Runs that returned `Ok`.
+    pub fn ok(&self) -> &Stats {
+        &self.ok
+    }
+
+    /// Runs that returned `Err`.
+    pub fn err(&self) -> &Stats {
+        &self.err
+    }
+
+    /// Runs that panicked.
+    pub fn panicked(&self) -> &Stats {
+        &self.panicked
+    }
+}
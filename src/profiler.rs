@@ -0,0 +1,670 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::lock::lock;
+use crate::trace::thread_id;
+use crate::{duration_to_human_string, Clock, MonotonicClock, TraceWriter};
+
+static NEXT_PROFILER: AtomicUsize = AtomicUsize::new(0);
+static NEXT_SPAN: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static STACK: RefCell<Vec<Frame>> = const { RefCell::new(Vec::new()) };
+}
+
+/// An open span on the stack of the current thread or task.
+#[derive(Debug, Clone)]
+struct Frame {
+    profiler: usize,
+    generation: u64,
+    span: u64,
+    node: usize,
+    /// Nanoseconds spent in closed child spans, shared by all copies of the
+    /// frame in [`SpanContext`]s.
+    children: Arc<AtomicU64>,
+    /// Set when the span is closed, so copies left on other stacks, e.g. by a
+    /// guard dropped on another thread, are skipped and pruned.
+    closed: Arc<AtomicBool>,
+}
+
+/// Records nested spans and aggregates them into a [`CallTree`].
+///
+/// Each thread keeps its own stack of open spans, so a span opened while
+/// another one is open on the same thread becomes its child. Async tasks that
+/// move between threads keep their own stack in a [`SpanContext`]. Clones
+/// share the same tree.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{ManualClock, Profiler};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// let profiler = Profiler::with_clock(clock.clone());
+/// for _ in 0..2 {
+///     let _request = profiler.span("request");
+///     clock.advance(Duration::from_millis(1));
+///     let _load = profiler.span("load");
+///     clock.advance(Duration::from_millis(2));
+///     {
+///         let _parse = profiler.span("parse");
+///         clock.advance(Duration::from_millis(5));
+///     }
+/// }
+///
+/// let tree = profiler.tree();
+/// let request = tree.roots().next().unwrap();
+/// assert_eq!("request", request.name());
+/// assert_eq!(2, request.calls());
+/// assert_eq!(Duration::from_millis(16), request.inclusive());
+/// assert_eq!(Duration::from_millis(2), request.exclusive());
+/// let load = request.children().next().unwrap();
+/// assert_eq!(Duration::from_millis(14), load.inclusive());
+/// assert_eq!(Duration::from_millis(4), load.exclusive());
+/// assert_eq!(Duration::from_millis(10), load.children().next().unwrap().exclusive());
+/// ```
+pub struct Profiler<C: Clock = MonotonicClock> {
+    inner: Arc<Inner<C>>,
+}
+
+struct Inner<C: Clock> {
+    id: usize,
+    clock: C,
+    epoch: C::Instant,
+    tree: Mutex<CallTree>,
+    log: Mutex<SpanLog>,
+}
+
+/// Finished spans kept for export, and listeners notified of each of them.
+#[derive(Default)]
+struct SpanLog {
+    keep: bool,
+    spans: Vec<SpanRecord>,
+    listeners: Vec<Listener>,
+}
+
+type Listener = Box<dyn FnMut(&SpanRecord) + Send>;
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler::with_clock(MonotonicClock)
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler::new()
+    }
+}
+
+impl<C: Clock> Clone for Profiler<C> {
+    fn clone(&self) -> Self {
+        Profiler {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<C: Clock> Profiler<C> {
+    pub fn with_clock(clock: C) -> Self {
+        Profiler {
+            inner: Arc::new(Inner {
+                id: NEXT_PROFILER.fetch_add(1, Ordering::Relaxed),
+                epoch: clock.now(),
+                clock,
+                tree: Mutex::new(CallTree::default()),
+                log: Mutex::new(SpanLog::default()),
+            }),
+        }
+    }
+
+    /// Opens a span named `name` as child of the innermost open span of this
+    /// profiler on the current thread, or in the entered [`SpanContext`]. The
+    /// span is closed when the returned guard is dropped.
+    pub fn span<S: AsRef<str>>(&self, name: S) -> Span<C> {
+        let id = self.inner.id;
+        let span = NEXT_SPAN.fetch_add(1, Ordering::Relaxed);
+        let frame = STACK.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            stack.retain(|frame| !frame.closed.load(Ordering::Relaxed));
+            let mut tree = self.lock();
+            let parent = stack.iter().rev()
+                .find(|frame| frame.profiler == id && frame.generation == tree.generation)
+                .map_or(ROOT, |frame| frame.node);
+            let frame = Frame {
+                profiler: id,
+                generation: tree.generation,
+                span,
+                node: tree.child(parent, name.as_ref()),
+                children: Arc::new(AtomicU64::new(0)),
+                closed: Arc::new(AtomicBool::new(false)),
+            };
+            stack.push(frame.clone());
+            frame
+        });
+        Span {
+            profiler: self.clone(),
+            frame,
+            started: self.inner.clock.now(),
+        }
+    }
+
+    /// Runs `f` inside a span named `name`.
+    pub fn in_span<S: AsRef<str>, T, F: FnOnce() -> T>(&self, name: S, f: F) -> T {
+        let _span = self.span(name);
+        f()
+    }
+
+    /// Returns a snapshot of the call tree recorded so far. Spans that are
+    /// still open are not counted yet.
+    pub fn tree(&self) -> CallTree {
+        self.lock().clone()
+    }
+
+    /// Removes everything recorded so far. Spans open during the reset are
+    /// not recorded when they close.
+    pub fn reset(&self) {
+        let mut tree = self.lock();
+        let generation = tree.generation + 1;
+        *tree = CallTree {
+            generation,
+            ..CallTree::default()
+        };
+    }
+
+    /// Keeps a [`SpanRecord`] of every span closed from now on, to be
+    /// returned by [`spans`](Profiler::spans).
+    pub fn keep_spans(&self) {
+        lock(&self.inner.log).keep = true;
+    }
+
+    /// Returns the spans kept since [`keep_spans`](Profiler::keep_spans) was
+    /// called, in the order they were closed.
+    pub fn spans(&self) -> Vec<SpanRecord> {
+        lock(&self.inner.log).spans.clone()
+    }
+
+    /// Calls `listener` with every span closed from now on. The listener must
+    /// not open spans on this profiler itself.
+    pub fn on_span<F: FnMut(&SpanRecord) + Send + 'static>(&self, listener: F) {
+        lock(&self.inner.log).listeners.push(Box::new(listener));
+    }
+
+    /// Writes every span closed from now on to `writer` as a complete event
+    /// of a Chrome trace (see [`TraceWriter`]), naming each thread on its first
+    /// span. The trace is never closed, which trace viewers accept, so it can
+    /// be followed while the process is running. Write errors stop the stream.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::Profiler;
+    /// use std::io::Write;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// #[derive(Clone, Default)]
+    /// struct Shared(Arc<Mutex<Vec<u8>>>);
+    ///
+    /// impl Write for Shared {
+    ///     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    ///         self.0.lock().unwrap().write(buf)
+    ///     }
+    ///
+    ///     fn flush(&mut self) -> std::io::Result<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let output = Shared::default();
+    /// let profiler = Profiler::new();
+    /// profiler.stream_chrome_trace(output.clone()).unwrap();
+    /// profiler.in_span("request", || ());
+    /// let json = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    /// assert_eq!(true, json.starts_with("[\n"));
+    /// assert_eq!(true, json.contains("\"name\":\"request\",\"ph\":\"X\""));
+    /// ```
+    pub fn stream_chrome_trace<W: Write + Send + 'static>(&self, writer: W) -> io::Result<()> {
+        let mut trace = Some(TraceWriter::new(writer)?);
+        let mut named = HashSet::new();
+        self.on_span(move |span| {
+            let result = trace.as_mut().map(|trace| {
+                if named.insert(span.thread_id()) {
+                    trace.thread_name(span.thread_id(), span.thread_name().unwrap_or("unnamed"))?;
+                }
+                trace.complete(span.name(), span.thread_id(), span.start(), span.duration(), &[])?;
+                trace.flush()
+            });
+            if let Some(Err(_)) = result {
+                trace = None;
+            }
+        });
+        Ok(())
+    }
+
+    /// Writes all kept spans as a Chrome trace, see
+    /// [`keep_spans`](Profiler::keep_spans).
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, Profiler};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let profiler = Profiler::with_clock(clock.clone());
+    /// profiler.keep_spans();
+    /// clock.advance(Duration::from_micros(5));
+    /// profiler.in_span("request", || clock.advance(Duration::from_micros(20)));
+    /// let json = String::from_utf8(profiler.write_chrome_trace(vec![]).unwrap()).unwrap();
+    /// assert_eq!(true, json.contains("\"name\":\"request\",\"ph\":\"X\",\"ts\":5.000,\"dur\":20.000"));
+    /// ```
+    pub fn write_chrome_trace<W: Write>(&self, writer: W) -> io::Result<W> {
+        let mut trace = TraceWriter::new(writer)?;
+        trace.profiler(self)?;
+        trace.finish()
+    }
+
+    fn close(&self, span: &Span<C>, elapsed: Duration) {
+        let id = self.inner.id;
+        span.frame.closed.store(true, Ordering::Relaxed);
+        STACK.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            if let Some(position) = stack.iter().rposition(|frame| frame.span == span.frame.span) {
+                stack.remove(position);
+                let parent = stack[..position].iter().rev()
+                    .find(|frame| frame.profiler == id && !frame.closed.load(Ordering::Relaxed));
+                if let Some(parent) = parent {
+                    let nanos = elapsed.as_nanos().min(u128::from(u64::MAX)) as u64;
+                    parent.children.fetch_add(nanos, Ordering::Relaxed);
+                }
+            }
+        });
+        let children = Duration::from_nanos(span.frame.children.load(Ordering::Relaxed));
+        let exclusive = elapsed.saturating_sub(children);
+        let path = {
+            let mut tree = self.lock();
+            if tree.generation != span.frame.generation {
+                return;
+            }
+            tree.record(span.frame.node, elapsed, exclusive);
+            tree.path(span.frame.node)
+        };
+
+        let mut log = lock(&self.inner.log);
+        if !log.keep && log.listeners.is_empty() {
+            return;
+        }
+        let record = SpanRecord {
+            path,
+            thread_id: thread_id(),
+            thread_name: std::thread::current().name().map(str::to_string),
+            start: self.inner.clock.duration_between(self.inner.epoch, span.started).unwrap_or_default(),
+            duration: elapsed,
+            exclusive,
+        };
+        for listener in &mut log.listeners {
+            listener(&record);
+        }
+        if log.keep {
+            log.spans.push(record);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CallTree> {
+        lock(&self.inner.tree)
+    }
+}
+
+/// A single finished span, see [`Profiler::keep_spans`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanRecord {
+    path: Vec<String>,
+    thread_id: u64,
+    thread_name: Option<String>,
+    start: Duration,
+    duration: Duration,
+    exclusive: Duration,
+}
+
+impl SpanRecord {
+    pub fn name(&self) -> &str {
+        self.path.last().map_or("", String::as_str)
+    }
+
+    /// Names of all enclosing spans, outermost first, ending with this span.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// Small sequential id of the thread the span was closed on.
+    pub fn thread_id(&self) -> u64 {
+        self.thread_id
+    }
+
+    pub fn thread_name(&self) -> Option<&str> {
+        self.thread_name.as_deref()
+    }
+
+    /// Start of the span relative to the creation of the profiler.
+    pub fn start(&self) -> Duration {
+        self.start
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Time spent in the span itself, excluding child spans.
+    pub fn exclusive(&self) -> Duration {
+        self.exclusive
+    }
+}
+
+/// Guard of an open span, see [`Profiler::span`].
+///
+/// The guard may be moved to another thread, e.g. when held across an
+/// `.await`. Its time only counts towards the parent span if it is dropped in
+/// the thread or [`SpanContext`] it was opened in. Either way spans opened
+/// after it was dropped are no longer its children.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::Profiler;
+/// use std::thread;
+///
+/// let profiler = Profiler::new();
+/// let moved = profiler.span("moved");
+/// thread::spawn(move || drop(moved)).join().unwrap();
+/// profiler.in_span("next", || ());
+///
+/// let tree = profiler.tree();
+/// let roots: Vec<_> = tree.roots().map(|node| node.name().to_string()).collect();
+/// assert_eq!(vec!["moved", "next"], roots);
+/// ```
+#[must_use = "the span is closed when the guard is dropped"]
+pub struct Span<C: Clock = MonotonicClock> {
+    profiler: Profiler<C>,
+    frame: Frame,
+    started: C::Instant,
+}
+
+impl<C: Clock> Drop for Span<C> {
+    fn drop(&mut self) {
+        let clock = &self.profiler.inner.clock;
+        let elapsed = clock.duration_between(self.started, clock.now()).unwrap_or_default();
+        self.profiler.close(self, elapsed);
+    }
+}
+
+/// Stack of open spans of an async task.
+///
+/// Entering the context replaces the span stack of the current thread with
+/// the one of the context until the closure returns, so spans opened by a
+/// task nest correctly even if every poll runs on a different thread. Usually
+/// this is done by [`FutureExt::in_current_span`](crate::FutureExt::in_current_span)
+/// around each poll.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{ManualClock, Profiler, SpanContext};
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// let profiler = Profiler::with_clock(clock.clone());
+/// let request = profiler.span("request");
+/// let mut context = SpanContext::current();
+/// let worker = profiler.clone();
+/// thread::spawn(move || {
+///     context.enter(|| worker.in_span("query", || clock.advance(Duration::from_millis(2))));
+/// }).join().unwrap();
+/// drop(request);
+///
+/// let tree = profiler.tree();
+/// let request = tree.roots().next().unwrap();
+/// assert_eq!("query", request.children().next().unwrap().name());
+/// assert_eq!(Duration::from_millis(2), request.inclusive());
+/// assert_eq!(Duration::ZERO, request.exclusive());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SpanContext {
+    stack: Vec<Frame>,
+}
+
+impl SpanContext {
+    /// An empty context, in which new spans are roots.
+    pub fn new() -> Self {
+        SpanContext::default()
+    }
+
+    /// A copy of the spans currently open on this thread, or in the entered
+    /// context. Spans opened in the copy become children of these spans, and
+    /// their time is not counted as exclusive time of the parents.
+    pub fn current() -> Self {
+        SpanContext {
+            stack: STACK.with(|stack| stack.borrow().clone()),
+        }
+    }
+
+    /// Runs `f` with this context as the span stack of the current thread.
+    pub fn enter<T, F: FnOnce() -> T>(&mut self, f: F) -> T {
+        struct Entered<'a>(&'a mut Vec<Frame>);
+
+        impl Drop for Entered<'_> {
+            fn drop(&mut self) {
+                STACK.with(|stack| std::mem::swap(&mut *stack.borrow_mut(), self.0));
+            }
+        }
+
+        STACK.with(|stack| std::mem::swap(&mut *stack.borrow_mut(), &mut self.stack));
+        let _entered = Entered(&mut self.stack);
+        f()
+    }
+}
+
+const ROOT: usize = 0;
+
+/// Spans aggregated by their call path, as recorded by a [`Profiler`].
+///
+/// Spans with the same name and the same chain of parents share a node, which
+/// sums up their inclusive time (including children), exclusive time
+/// (excluding children) and number of calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallTree {
+    nodes: Vec<NodeData>,
+    /// Incremented on every [`Profiler::reset`] to invalidate open spans.
+    generation: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NodeData {
+    name: String,
+    parent: usize,
+    children: Vec<usize>,
+    calls: u64,
+    inclusive: Duration,
+    exclusive: Duration,
+}
+
+impl NodeData {
+    fn new(name: &str, parent: usize) -> Self {
+        NodeData {
+            name: name.to_string(),
+            parent,
+            children: vec![],
+            calls: 0,
+            inclusive: Duration::default(),
+            exclusive: Duration::default(),
+        }
+    }
+}
+
+impl Default for CallTree {
+    fn default() -> Self {
+        CallTree {
+            nodes: vec![NodeData::new("", ROOT)],
+            generation: 0,
+        }
+    }
+}
+
+impl CallTree {
+    /// Top level nodes in the order they were first entered.
+    pub fn roots(&self) -> impl Iterator<Item = Node<'_>> {
+        self.node(ROOT).children()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes[ROOT].children.is_empty()
+    }
+
+    /// Sum of the inclusive time of all top level nodes.
+    pub fn total(&self) -> Duration {
+        self.roots().map(|node| node.inclusive()).sum()
+    }
+
+    /// Visits all nodes depth first, passing each node with its depth (0 for
+    /// top level nodes).
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::Profiler;
+    ///
+    /// let profiler = Profiler::new();
+    /// profiler.in_span("a", || profiler.in_span("b", || ()));
+    /// let mut visited = vec![];
+    /// profiler.tree().walk(|node, depth| visited.push((node.name().to_string(), depth)));
+    /// assert_eq!(vec![("a".to_string(), 0), ("b".to_string(), 1)], visited);
+    /// ```
+    pub fn walk<F: FnMut(Node<'_>, usize)>(&self, mut visitor: F) {
+        fn visit<F: FnMut(Node<'_>, usize)>(node: Node<'_>, depth: usize, visitor: &mut F) {
+            visitor(node, depth);
+            for child in node.children() {
+                visit(child, depth + 1, visitor);
+            }
+        }
+        for root in self.roots() {
+            visit(root, 0, &mut visitor);
+        }
+    }
+
+    /// Renders the tree as an indented text report, one node per line.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, Profiler};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let profiler = Profiler::with_clock(clock.clone());
+    /// profiler.in_span("request", || {
+    ///     clock.advance(Duration::from_millis(3));
+    ///     profiler.in_span("parse", || clock.advance(Duration::from_millis(40)));
+    /// });
+    /// assert_eq!(
+    ///     "request 43ms (exclusive 3ms, 1 call)\n  parse 40ms (exclusive 40ms, 1 call)\n",
+    ///     profiler.tree().render()
+    /// );
+    /// ```
+    pub fn render(&self) -> String {
+        let mut report = String::new();
+        self.walk(|node, depth| {
+            report.push_str(&format!(
+                "{}{} {} (exclusive {}, {} {})\n",
+                "  ".repeat(depth),
+                node.name(),
+                duration_to_human_string(&node.inclusive()),
+                duration_to_human_string(&node.exclusive()),
+                node.calls(),
+                if node.calls() == 1 { "call" } else { "calls" },
+            ));
+        });
+        report
+    }
+
+    fn node(&self, index: usize) -> Node<'_> {
+        Node { tree: self, index }
+    }
+
+    fn child(&mut self, parent: usize, name: &str) -> usize {
+        let existing = self.nodes[parent].children.iter().copied().find(|child| self.nodes[*child].name == name);
+        existing.unwrap_or_else(|| {
+            self.nodes.push(NodeData::new(name, parent));
+            let child = self.nodes.len() - 1;
+            self.nodes[parent].children.push(child);
+            child
+        })
+    }
+
+    /// Names from the outermost node down to `node`.
+    fn path(&self, mut node: usize) -> Vec<String> {
+        let mut path = vec![];
+        while node != ROOT {
+            path.push(self.nodes[node].name.clone());
+            node = self.nodes[node].parent;
+        }
+        path.reverse();
+        path
+    }
+
+    fn record(&mut self, node: usize, inclusive: Duration, exclusive: Duration) {
+        let node = &mut self.nodes[node];
+        node.calls += 1;
+        node.inclusive += inclusive;
+        node.exclusive += exclusive;
+    }
+}
+
+impl fmt::Display for CallTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+/// A node of a [`CallTree`].
+#[derive(Debug, Clone, Copy)]
+pub struct Node<'a> {
+    tree: &'a CallTree,
+    index: usize,
+}
+
+impl<'a> Node<'a> {
+    pub fn name(&self) -> &'a str {
+        &self.data().name
+    }
+
+    /// Number of finished spans aggregated into this node.
+    pub fn calls(&self) -> u64 {
+        self.data().calls
+    }
+
+    /// Total time spent in this node, including its children.
+    pub fn inclusive(&self) -> Duration {
+        self.data().inclusive
+    }
+
+    /// Total time spent in this node itself, excluding its children.
+    pub fn exclusive(&self) -> Duration {
+        self.data().exclusive
+    }
+
+    /// Child nodes in the order they were first entered.
+    pub fn children(&self) -> impl Iterator<Item = Node<'a>> {
+        let tree = self.tree;
+        self.data().children.iter().map(move |index| tree.node(*index))
+    }
+
+    fn data(&self) -> &'a NodeData {
+        &self.tree.nodes[self.index]
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::sync::{OnceLock, RwLock, RwLockReadGuard};
+use std::time::Duration;
+
+use crate::lock::{read, write};
+use crate::{Histogram, Sink, SyncTimer};
+
+static GLOBAL: OnceLock<Registry> = OnceLock::new();
+
+/// Named [`SyncTimer`]s, created on first use.
+///
+/// Usually the process wide registry is used through [`global`] and
+/// [`registry`], but separate registries can be created as well. Timers can
+/// carry labels, which are shown in their names as `name{label="value"}`.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::Registry;
+/// use std::time::Duration;
+///
+/// let registry = Registry::new();
+/// registry.timer("db.query").record(Duration::from_millis(3));
+/// registry.timer("db.query").record(Duration::from_millis(5));
+/// registry.labeled("http.request", &[("method", "GET")]).record(Duration::from_millis(40));
+///
+/// assert_eq!(vec!["db.query", "http.request"], registry.names());
+/// assert_eq!(Duration::from_millis(8), registry.histograms()[0].1.sum());
+/// assert_eq!("http.request{method=\"GET\"}", registry.histograms()[1].0);
+/// assert_eq!(true, registry.get("cache.hit").is_none());
+/// ```
+pub struct Registry {
+    timers: RwLock<BTreeMap<Key, SyncTimer>>,
+    buckets: RwLock<Vec<Duration>>,
+    quantiles: RwLock<Vec<f64>>,
+}
+
+/// Name and labels, sorted by label name, of a timer.
+pub(crate) type Key = (String, Vec<(String, String)>);
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry {
+            timers: RwLock::new(BTreeMap::new()),
+            buckets: RwLock::new(DEFAULT_BUCKETS.iter().map(|millis| Duration::from_millis(*millis)).collect()),
+            quantiles: RwLock::new(vec![]),
+        }
+    }
+
+    /// Returns the timer named `name`, creating it if it does not exist yet.
+    pub fn timer(&self, name: &str) -> SyncTimer {
+        self.labeled(name, &[])
+    }
+
+    /// Returns the timer named `name` with the given labels, creating it if it
+    /// does not exist yet. The order of the labels does not matter. The labels
+    /// `le` and `quantile` hold the bucket bounds and quantiles of the rendered
+    /// metrics, so they are renamed to `le_` and `quantile_`.
+    pub fn labeled(&self, name: &str, labels: &[(&str, &str)]) -> SyncTimer {
+        let mut labels: Vec<(String, String)> = labels.iter()
+            .map(|(label, value)| match *label {
+                "le" | "quantile" => (format!("{}_", label), value.to_string()),
+                label => (label.to_string(), value.to_string()),
+            })
+            .collect();
+        labels.sort();
+        let key = (name.to_string(), labels);
+        if let Some(timer) = self.read().get(&key) {
+            return timer.clone();
+        }
+        write(&self.timers).entry(key).or_default().clone()
+    }
+
+    /// Returns the timer named `name` without labels if it exists.
+    pub fn get(&self, name: &str) -> Option<SyncTimer> {
+        self.read().get(&(name.to_string(), vec![])).cloned()
+    }
+
+    /// Removes the timer named `name` without labels from the registry. Clones
+    /// of it keep working but are no longer reachable through the registry.
+    pub fn remove(&self, name: &str) -> Option<SyncTimer> {
+        write(&self.timers).remove(&(name.to_string(), vec![]))
+    }
+
+    /// Names of all timers, in alphabetical order and without labels.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.read().keys().map(|(name, _)| name.clone()).collect();
+        names.dedup();
+        names
+    }
+
+    /// All timers with their names and labels, in alphabetical order.
+    pub fn timers(&self) -> Vec<(String, SyncTimer)> {
+        self.entries().into_iter().map(|(key, timer)| (display(&key), timer)).collect()
+    }
+
+    /// Snapshot of the histograms of all timers, in alphabetical order.
+    pub fn histograms(&self) -> Vec<(String, Histogram)> {
+        self.timers().into_iter().map(|(name, timer)| (name, timer.histogram())).collect()
+    }
+
+    /// Upper bounds of the histogram buckets in the metrics rendered by
+    /// [`render_prometheus`](Registry::render_prometheus) and
+    /// [`render_openmetrics`](Registry::render_openmetrics). By default these
+    /// are the buckets of the Prometheus client libraries, from 5ms to 10s.
+    pub fn set_buckets(&self, mut buckets: Vec<Duration>) {
+        buckets.sort();
+        buckets.dedup();
+        *write(&self.buckets) = buckets;
+    }
+
+    pub fn buckets(&self) -> Vec<Duration> {
+        read(&self.buckets).clone()
+    }
+
+    /// Renders the timers as summaries with the given quantiles (`0.0..=1.0`)
+    /// instead of histograms. An empty list switches back to histograms.
+    ///
+    /// # Panics:
+    /// Panics if a quantile is outside of `0.0..=1.0`.
+    pub fn set_quantiles(&self, quantiles: Vec<f64>) {
+        assert!(quantiles.iter().all(|quantile| (0.0..=1.0).contains(quantile)), "Quantiles must be within 0.0..=1.0!");
+        *write(&self.quantiles) = quantiles;
+    }
+
+    pub fn quantiles(&self) -> Vec<f64> {
+        read(&self.quantiles).clone()
+    }
+
+    /// All timers with their keys, in alphabetical order.
+    pub(crate) fn entries(&self) -> Vec<(Key, SyncTimer)> {
+        self.read().iter().map(|(key, timer)| (key.clone(), timer.clone())).collect()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, BTreeMap<Key, SyncTimer>> {
+        read(&self.timers)
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry::new()
+    }
+}
+
+/// Default bucket bounds in milliseconds.
+const DEFAULT_BUCKETS: [u64; 11] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// `name{label="value",...}`, or just `name` without labels.
+fn display((name, labels): &Key) -> String {
+    if labels.is_empty() {
+        return name.clone();
+    }
+    let labels: Vec<String> = labels.iter().map(|(label, value)| format!("{}={:?}", label, value)).collect();
+    format!("{}{{{}}}", name, labels.join(","))
+}
+
+/// Records every measurement into the timer of the same name.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{registry, Timed};
+///
+/// {
+///     let _timed = Timed::new("registry.sink", None, registry());
+/// }
+/// assert_eq!(1, yatl::global("registry.sink").count());
+/// ```
+impl Sink for Registry {
+    fn record(&self, name: &str, elapsed: Duration) {
+        self.timer(name).record(elapsed);
+    }
+}
+
+impl Sink for &Registry {
+    fn record(&self, name: &str, elapsed: Duration) {
+        self.timer(name).record(elapsed);
+    }
+}
+
+/// The process wide [`Registry`].
+pub fn registry() -> &'static Registry {
+    GLOBAL.get_or_init(Registry::new)
+}
+
+/// Returns the timer named `name` from the process wide [`Registry`], creating
+/// it if it does not exist yet.
+///
+/// # Examples:
+///
+/// ```
+/// use std::thread;
+///
+/// let workers: Vec<_> = (0..4).map(|_| thread::spawn(|| {
+///     for _ in 0..10 {
+///         yatl::global("db.query").time(|| thread::yield_now());
+///     }
+/// })).collect();
+/// for worker in workers {
+///     worker.join().unwrap();
+/// }
+/// assert_eq!(40, yatl::global("db.query").count());
+/// ```
+pub fn global(name: &str) -> SyncTimer {
+    registry().timer(name)
+}
@@ -0,0 +1,151 @@
+use std::ops::{Deref, DerefMut};
+use std::thread;
+use std::time::Duration;
+
+use crate::{Clock, LapMeta, Timer};
+
+/// Guard returned by [`Timer::scope`] that records a named lap when it goes
+/// out of scope.
+///
+/// The lap is recorded on every way out of the block, including early returns,
+/// `?` and panics. Laps recorded while unwinding from a panic carry the tag
+/// `panicked` with the value `true`. Like any lap, its split covers the time
+/// since the previous lap. The time spent in the scope itself, including
+/// nested laps, is returned by [`finish`](Scope::finish) and kept in the tag
+/// `duration` in the `Debug` format of [`Duration`], which
+/// [`parse_duration`](crate::parse_duration) reads back. The guard
+/// dereferences to the timer, so laps and nested scopes can be taken through
+/// it.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{parse_duration, ManualClock, Timer};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// let mut timer = Timer::with_clock(clock.clone());
+/// {
+///     let mut request = timer.scope("request");
+///     clock.advance(Duration::from_millis(2));
+///     {
+///         let _parse = request.scope("parse");
+///         clock.advance(Duration::from_millis(3));
+///     }
+/// }
+/// assert_eq!(Duration::from_millis(5), timer.find_lap("parse").unwrap().time());
+/// assert_eq!(Duration::from_millis(5), timer.find_lap("request").unwrap().time());
+/// assert_eq!(Duration::from_millis(0), timer.find_lap("request").unwrap().split());
+/// assert_eq!(Some("3ms"), timer.find_lap("parse").unwrap().tag("duration"));
+/// let request = timer.find_lap("request").unwrap().tag("duration").unwrap();
+/// assert_eq!(Duration::from_millis(5), parse_duration(request).unwrap());
+/// ```
+///
+/// ## Recording during a panic:
+/// ```
+/// use yatl::Timer;
+/// use std::panic::{catch_unwind, AssertUnwindSafe};
+///
+/// let mut timer = Timer::new();
+/// let result = catch_unwind(AssertUnwindSafe(|| {
+///     let _scope = timer.scope("work");
+///     panic!("Failed to work");
+/// }));
+/// assert_eq!(true, result.is_err());
+/// assert_eq!(Some("true"), timer.find_lap("work").unwrap().tag("panicked"));
+/// ```
+#[must_use = "the scope records its lap when it is dropped"]
+pub struct Scope<'a, C: Clock> {
+    timer: &'a mut Timer<C>,
+    name: Option<String>,
+    started: Duration,
+}
+
+impl<'a, C: Clock> Scope<'a, C> {
+    pub(crate) fn new(timer: &'a mut Timer<C>, name: String) -> Self {
+        let started = timer.elapsed().unwrap_or_default();
+        Scope {
+            timer,
+            name: Some(name),
+            started,
+        }
+    }
+
+    /// Records the lap now and returns the running time spent in the scope.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// timer.start();
+    /// clock.advance(Duration::from_millis(4));
+    /// let scope = timer.scope("load");
+    /// clock.advance(Duration::from_millis(6));
+    /// assert_eq!(Duration::from_millis(6), scope.finish());
+    /// assert_eq!(Duration::from_millis(10), timer.find_lap("load").unwrap().time());
+    /// ```
+    pub fn finish(mut self) -> Duration {
+        self.record(false)
+    }
+
+    /// Drops the scope without recording a lap.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::Timer;
+    ///
+    /// let mut timer = Timer::new();
+    /// timer.scope("cached").discard();
+    /// assert_eq!(true, timer.laps().is_empty());
+    /// ```
+    pub fn discard(mut self) {
+        self.name = None;
+    }
+
+    fn record(&mut self, panicked: bool) -> Duration {
+        let name = match self.name.take() {
+            Some(name) => name,
+            None => return Duration::default(),
+        };
+        let started = self.started;
+        let meta = |time: Duration, meta: LapMeta| {
+            let meta = meta.name(name).tag("duration", format!("{:?}", time.saturating_sub(started)));
+            if panicked {
+                meta.tag("panicked", true)
+            } else {
+                meta
+            }
+        };
+        // A stopped timer refuses the lap, the time spent is still reported.
+        let time = match self.timer.lap_at(meta) {
+            Ok(time) => time,
+            Err(_) => self.timer.elapsed().unwrap_or_default(),
+        };
+        time.saturating_sub(self.started)
+    }
+}
+
+impl<'a, C: Clock> Deref for Scope<'a, C> {
+    type Target = Timer<C>;
+
+    fn deref(&self) -> &Timer<C> {
+        self.timer
+    }
+}
+
+impl<'a, C: Clock> DerefMut for Scope<'a, C> {
+    fn deref_mut(&mut self) -> &mut Timer<C> {
+        self.timer
+    }
+}
+
+impl<'a, C: Clock> Drop for Scope<'a, C> {
+    fn drop(&mut self) {
+        self.record(thread::panicking());
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use crate::{duration_to_human_string, Timer};
+
+/// Receiver of named measurements, e.g. from [`Timed`] or the `#[timed]`
+/// attribute.
+///
+/// Implemented for closures taking the name and the measured duration.
+pub trait Sink {
+    fn record(&self, name: &str, elapsed: Duration);
+}
+
+impl<F: Fn(&str, Duration)> Sink for F {
+    fn record(&self, name: &str, elapsed: Duration) {
+        self(name, elapsed)
+    }
+}
+
+/// Prints every measurement to stderr as `name took 12ms`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StderrSink;
+
+impl Sink for StderrSink {
+    fn record(&self, name: &str, elapsed: Duration) {
+        eprintln!("{} took {}", name, duration_to_human_string(&elapsed));
+    }
+}
+
+/// Guard that measures the time until it is dropped and hands it to a
+/// [`Sink`] if it reaches the threshold.
+///
+/// This is what the `#[timed]` attribute expands to.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::Timed;
+/// use std::cell::RefCell;
+/// use std::time::Duration;
+///
+/// let recorded = RefCell::new(vec![]);
+/// let sink = |name: &str, elapsed: Duration| recorded.borrow_mut().push((name.to_string(), elapsed));
+/// {
+///     let _timed = Timed::new("fast", Some(Duration::from_secs(60)), &sink);
+/// }
+/// {
+///     let _timed = Timed::new("always", None, &sink);
+/// }
+/// let recorded = recorded.borrow();
+/// assert_eq!(1, recorded.len());
+/// assert_eq!("always", recorded[0].0);
+/// ```
+pub struct Timed<S: Sink> {
+    name: &'static str,
+    threshold: Option<Duration>,
+    sink: S,
+    timer: Timer,
+}
+
+impl<S: Sink> Timed<S> {
+    pub fn new(name: &'static str, threshold: Option<Duration>, sink: S) -> Self {
+        let mut timer = Timer::new();
+        let _ = timer.start();
+        Timed {
+            name,
+            threshold,
+            sink,
+            timer,
+        }
+    }
+}
+
+impl<S: Sink> Drop for Timed<S> {
+    fn drop(&mut self) {
+        if let Ok(elapsed) = self.timer.lap() {
+            if self.threshold.into_iter().all(|threshold| elapsed >= threshold) {
+                self.sink.record(self.name, elapsed);
+            }
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::Level;
+
+use crate::lock::lock;
+use crate::{duration_to_human_string, Clock, MonotonicClock, Timer};
+
+/// Emits a `log` record for every operation taking longer than its threshold.
+///
+/// Records go to the target `yatl::slow` and contain the name of the
+/// operation, its duration, the threshold and the laps of its [`Timer`], e.g.
+/// `db.query took 250ms (threshold 100ms): connect 2ms, query 150ms, rest 98ms`.
+/// With a [rate limit](SlowLogger::rate_limit) repeated records of the same
+/// operation are suppressed and counted instead.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::{ManualClock, SlowLogger};
+/// use std::sync::Mutex;
+/// use std::time::Duration;
+///
+/// static RECORDS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+///
+/// struct Capture;
+///
+/// impl log::Log for Capture {
+///     fn enabled(&self, _: &log::Metadata) -> bool {
+///         true
+///     }
+///
+///     fn log(&self, record: &log::Record) {
+///         RECORDS.lock().unwrap().push(format!("{} {}", record.level(), record.args()));
+///     }
+///
+///     fn flush(&self) {}
+/// }
+///
+/// log::set_logger(&Capture).unwrap();
+/// log::set_max_level(log::LevelFilter::Trace);
+///
+/// let clock = ManualClock::new();
+/// let slow = SlowLogger::with_clock(clock.clone())
+///     .threshold(Duration::from_millis(100))
+///     .threshold_for("db.query", Duration::from_millis(500));
+/// {
+///     let mut query = slow.start("db.query");
+///     clock.advance(Duration::from_millis(200));
+///     query.lap_named("connect");
+///     clock.advance(Duration::from_millis(450));
+///     query.lap_named("fetch");
+///     clock.advance(Duration::from_millis(50));
+/// }
+/// drop(slow.start("render"));
+///
+/// assert_eq!(
+///     vec!["WARN db.query took 700ms (threshold 500ms): connect 200ms, fetch 450ms, rest 50ms"],
+///     *RECORDS.lock().unwrap()
+/// );
+/// ```
+pub struct SlowLogger<C: Clock = MonotonicClock> {
+    clock: C,
+    level: Level,
+    threshold: Duration,
+    thresholds: HashMap<String, Duration>,
+    rate_limit: Option<Duration>,
+    /// Time of the last record and number of records suppressed since then,
+    /// by operation.
+    limited: Mutex<HashMap<String, (C::Instant, u64)>>,
+}
+
+impl SlowLogger {
+    pub fn new() -> Self {
+        SlowLogger::with_clock(MonotonicClock)
+    }
+}
+
+impl Default for SlowLogger {
+    fn default() -> Self {
+        SlowLogger::new()
+    }
+}
+
+impl<C: Clock> SlowLogger<C> {
+    /// Logs at level `Warn` for operations over 100ms, without rate limit.
+    pub fn with_clock(clock: C) -> Self {
+        SlowLogger {
+            clock,
+            level: Level::Warn,
+            threshold: Duration::from_millis(100),
+            thresholds: HashMap::new(),
+            rate_limit: None,
+            limited: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Threshold of all operations without their own threshold.
+    pub fn threshold(mut self, threshold: Duration) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets a separate threshold for the operation `name`.
+    pub fn threshold_for<S: Into<String>>(mut self, name: S, threshold: Duration) -> Self {
+        self.thresholds.insert(name.into(), threshold);
+        self
+    }
+
+    /// Logs each operation at most once per `interval`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, SlowLogger, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let slow = SlowLogger::with_clock(clock.clone()).rate_limit(Duration::from_secs(60));
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// timer.start();
+    /// clock.advance(Duration::from_secs(1));
+    ///
+    /// assert_eq!(true, slow.check("sync", &timer));
+    /// assert_eq!(false, slow.check("sync", &timer));
+    /// assert_eq!(true, slow.check("upload", &timer));
+    /// clock.advance(Duration::from_secs(60));
+    /// // Logged with "(1 similar suppressed)" appended.
+    /// assert_eq!(true, slow.check("sync", &timer));
+    /// ```
+    pub fn rate_limit(mut self, interval: Duration) -> Self {
+        self.rate_limit = Some(interval);
+        self
+    }
+
+    /// Threshold of the operation `name`.
+    pub fn threshold_of(&self, name: &str) -> Duration {
+        self.thresholds.get(name).copied().unwrap_or(self.threshold)
+    }
+
+    /// Starts timing the operation `name`, which is checked when the returned
+    /// guard is dropped. The guard dereferences to its timer, so laps can be
+    /// taken through it.
+    pub fn start<S: Into<String>>(&self, name: S) -> SlowOperation<'_, C>
+    where
+        C: Clone,
+    {
+        let mut timer = Timer::with_clock(self.clock.clone());
+        let _ = timer.start();
+        SlowOperation {
+            logger: self,
+            name: name.into(),
+            timer,
+        }
+    }
+
+    /// Logs the running time and laps of `timer` if it exceeds the threshold
+    /// of `name`. Returns whether the operation was logged, which is not the
+    /// case if it is fast enough or suppressed by the rate limit.
+    pub fn check<T: Clock>(&self, name: &str, timer: &Timer<T>) -> bool {
+        let elapsed = match timer.elapsed() {
+            Ok(elapsed) => elapsed,
+            Err(_) => return false,
+        };
+        let threshold = self.threshold_of(name);
+        if elapsed <= threshold {
+            return false;
+        }
+        let suppressed = match self.admit(name) {
+            Some(suppressed) => suppressed,
+            None => return false,
+        };
+
+        let mut message = format!(
+            "{} took {} (threshold {})",
+            name,
+            duration_to_human_string(&elapsed),
+            duration_to_human_string(&threshold),
+        );
+        let laps = timer.lap_records();
+        if !laps.is_empty() {
+            let mut parts: Vec<String> = laps.iter()
+                .map(|lap| {
+                    let split = duration_to_human_string(&lap.split());
+                    match lap.name() {
+                        Some(name) => format!("{} {}", name, split),
+                        None => format!("lap {} {}", lap.index(), split),
+                    }
+                })
+                .collect();
+            let rest = elapsed.saturating_sub(laps[laps.len() - 1].time());
+            if rest > Duration::default() {
+                parts.push(format!("rest {}", duration_to_human_string(&rest)));
+            }
+            message.push_str(": ");
+            message.push_str(&parts.join(", "));
+        }
+        if suppressed > 0 {
+            message.push_str(&format!(" ({} similar suppressed)", suppressed));
+        }
+        log::log!(target: "yatl::slow", self.level, "{}", message);
+        true
+    }
+
+    /// Returns the number of records of `name` suppressed since the last one,
+    /// or `None` if this one has to be suppressed as well.
+    fn admit(&self, name: &str) -> Option<u64> {
+        let interval = match self.rate_limit {
+            Some(interval) => interval,
+            None => return Some(0),
+        };
+        let now = self.clock.now();
+        let mut limited = lock(&self.limited);
+        match limited.get_mut(name) {
+            Some((last, suppressed)) => {
+                let since = self.clock.duration_between(*last, now).unwrap_or_default();
+                if since < interval {
+                    *suppressed += 1;
+                    return None;
+                }
+                let count = *suppressed;
+                *last = now;
+                *suppressed = 0;
+                Some(count)
+            }
+            None => {
+                limited.insert(name.to_string(), (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+/// Guard of an operation timed by a [`SlowLogger`], see
+/// [`SlowLogger::start`].
+#[must_use = "the operation is checked when the guard is dropped"]
+pub struct SlowOperation<'a, C: Clock> {
+    logger: &'a SlowLogger<C>,
+    name: String,
+    timer: Timer<C>,
+}
+
+impl<'a, C: Clock> Deref for SlowOperation<'a, C> {
+    type Target = Timer<C>;
+
+    fn deref(&self) -> &Timer<C> {
+        &self.timer
+    }
+}
+
+impl<'a, C: Clock> DerefMut for SlowOperation<'a, C> {
+    fn deref_mut(&mut self) -> &mut Timer<C> {
+        &mut self.timer
+    }
+}
+
+impl<'a, C: Clock> Drop for SlowOperation<'a, C> {
+    fn drop(&mut self) {
+        self.logger.check(&self.name, &self.timer);
+    }
+}
@@ -0,0 +1,186 @@
+use std::iter::FromIterator;
+use std::time::Duration;
+
+use crate::{Clock, Timer};
+
+/// Summary statistics over a set of durations.
+///
+/// All statistics except [`count`](Stats::count) and [`total`](Stats::total)
+/// are `None` for an empty set. A single sample is its own minimum, maximum,
+/// mean, median and percentile, with a standard deviation and MAD of zero.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::Stats;
+/// use std::time::Duration;
+///
+/// let stats: Stats = [4, 1, 3, 2].iter().map(|ms| Duration::from_millis(*ms)).collect();
+/// assert_eq!(4, stats.count());
+/// assert_eq!(Duration::from_millis(10), stats.total());
+/// assert_eq!(Some(Duration::from_millis(1)), stats.min());
+/// assert_eq!(Some(Duration::from_millis(4)), stats.max());
+/// assert_eq!(Some(Duration::from_micros(2500)), stats.mean());
+/// assert_eq!(Some(Duration::from_micros(2500)), stats.median());
+/// assert_eq!(Some(Duration::from_millis(1)), stats.mad());
+/// ```
+///
+/// ## Empty and single-sample sets:
+/// ```
+/// use yatl::Stats;
+/// use std::time::Duration;
+///
+/// let empty = Stats::from_durations(vec![]);
+/// assert_eq!(0, empty.count());
+/// assert_eq!(None, empty.mean());
+/// assert_eq!(None, empty.percentile(99.0));
+///
+/// let single = Stats::from_durations(vec![Duration::from_millis(7)]);
+/// assert_eq!(Some(Duration::from_millis(7)), single.percentile(99.0));
+/// assert_eq!(Some(Duration::from_secs(0)), single.std_dev());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    sorted: Vec<Duration>,
+    total: Duration,
+}
+
+impl Stats {
+    pub fn from_durations<I: IntoIterator<Item = Duration>>(durations: I) -> Self {
+        let mut sorted: Vec<Duration> = durations.into_iter().collect();
+        sorted.sort();
+        let total = sorted.iter().sum();
+        Stats { sorted, total }
+    }
+
+    /// Statistics over the cumulative laps of a timer.
+    pub fn from_laps<C: Clock>(timer: &Timer<C>) -> Self {
+        Stats::from_durations(timer.laps())
+    }
+
+    /// Statistics over the splits of a timer.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, Stats, Timer};
+    /// use std::time::Duration;
+    ///
+    /// let mut timer = Timer::with_clock(ManualClock::auto_advancing(Duration::from_millis(3)));
+    /// timer.start();
+    /// for _ in 0..10 {
+    ///     timer.lap();
+    /// }
+    /// assert_eq!(Some(Duration::from_millis(3)), Stats::from_splits(&timer).max());
+    /// assert_eq!(Some(Duration::from_millis(30)), Stats::from_laps(&timer).max());
+    /// ```
+    pub fn from_splits<C: Clock>(timer: &Timer<C>) -> Self {
+        Stats::from_durations(timer.splits())
+    }
+
+    pub fn count(&self) -> usize {
+        self.sorted.len()
+    }
+
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.sorted.first().copied()
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.sorted.last().copied()
+    }
+
+    /// Arithmetic mean.
+    pub fn mean(&self) -> Option<Duration> {
+        if self.sorted.is_empty() {
+            return None;
+        }
+        Some(from_nanos(self.total.as_nanos() as f64 / self.count() as f64))
+    }
+
+    pub fn median(&self) -> Option<Duration> {
+        self.percentile(50.0)
+    }
+
+    /// Population standard deviation.
+    pub fn std_dev(&self) -> Option<Duration> {
+        let mean = self.mean()?.as_nanos() as f64;
+        let variance = self.sorted.iter()
+            .map(|d| (d.as_nanos() as f64 - mean).powi(2))
+            .sum::<f64>() / self.count() as f64;
+        Some(from_nanos(variance.sqrt()))
+    }
+
+    /// Median absolute deviation from the median.
+    pub fn mad(&self) -> Option<Duration> {
+        let median = self.median()?;
+        let deviations = self.sorted.iter().map(|d| (*d).max(median) - (*d).min(median));
+        Stats::from_durations(deviations).median()
+    }
+
+    /// Returns the `p`th percentile (`p` between 0 and 100), interpolating
+    /// linearly between the two closest samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is not within `0.0..=100.0`.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::Stats;
+    /// use std::time::Duration;
+    ///
+    /// let stats = Stats::from_durations((1..=1000).map(Duration::from_micros));
+    /// assert_eq!(Some(Duration::from_nanos(500_500)), stats.percentile(50.0));
+    /// assert_eq!(Some(Duration::from_nanos(990_010)), stats.percentile(99.0));
+    /// assert_eq!(Some(Duration::from_nanos(999_001)), stats.percentile(99.9));
+    /// assert_eq!(Some(Duration::from_micros(1000)), stats.percentile(100.0));
+    /// ```
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        assert!((0.0..=100.0).contains(&p), "Percentile {} out of range 0..=100", p);
+        if self.sorted.is_empty() {
+            return None;
+        }
+        let rank = p / 100.0 * (self.count() - 1) as f64;
+        let lower = self.sorted[rank.floor() as usize];
+        let upper = self.sorted[rank.ceil() as usize];
+        let offset = (upper - lower).as_nanos() as f64 * rank.fract();
+        Some(lower + from_nanos(offset))
+    }
+
+    pub fn p50(&self) -> Option<Duration> {
+        self.percentile(50.0)
+    }
+
+    pub fn p90(&self) -> Option<Duration> {
+        self.percentile(90.0)
+    }
+
+    pub fn p99(&self) -> Option<Duration> {
+        self.percentile(99.0)
+    }
+
+    pub fn p999(&self) -> Option<Duration> {
+        self.percentile(99.9)
+    }
+
+    /// All samples in ascending order.
+    pub fn samples(&self) -> &[Duration] {
+        &self.sorted
+    }
+}
+
+impl FromIterator<Duration> for Stats {
+    fn from_iter<I: IntoIterator<Item = Duration>>(iter: I) -> Self {
+        Stats::from_durations(iter)
+    }
+}
+
+fn from_nanos(nanos: f64) -> Duration {
+    Duration::from_nanos(nanos.round() as u64)
+}
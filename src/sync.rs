@@ -0,0 +1,471 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::lock::lock;
+use crate::trace::thread_id;
+use crate::{Clock, Error, Histogram, MonotonicClock, Result, Sink, Stats};
+
+/// Number of independently locked histograms of a [`SyncTimer`].
+const SHARDS: usize = 4;
+
+/// Number of most recent exemplars kept by a [`SyncTimer`].
+const EXEMPLARS: usize = 64;
+
+thread_local! {
+    static BUFFERS: RefCell<Buffers> = const { RefCell::new(Buffers(Vec::new())) };
+}
+
+/// Timer that can be shared between threads and records from all of them
+/// through `&self`.
+///
+/// Samples are recorded into several separately locked [`Histogram`]s by
+/// thread, so threads rarely wait for each other and memory use does not grow
+/// with the number of samples. Each histogram is only allocated once a sample
+/// is recorded into it. Clones share the same histograms.
+/// [`histogram`](SyncTimer::histogram) merges them into a snapshot of the
+/// samples of all threads. The exact samples needed for
+/// [`stats`](SyncTimer::stats) are only kept after
+/// [`set_keep_samples`](SyncTimer::set_keep_samples).
+///
+/// The timer runs from its creation, there is no `start()` or `pause()`.
+///
+/// On hot paths [`record_local`](SyncTimer::record_local) avoids even that
+/// contention by collecting samples in a buffer of the current thread first.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::SyncTimer;
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let timer = SyncTimer::new();
+/// let workers: Vec<_> = (1..=4).map(|worker| {
+///     let timer = timer.clone();
+///     thread::spawn(move || {
+///         for _ in 0..100 {
+///             timer.record(Duration::from_millis(worker));
+///         }
+///     })
+/// }).collect();
+/// for worker in workers {
+///     worker.join().unwrap();
+/// }
+///
+/// let histogram = timer.histogram();
+/// assert_eq!(400, histogram.count());
+/// assert_eq!(Duration::from_secs(1), histogram.sum());
+/// assert_eq!(Duration::from_millis(4), histogram.max());
+/// assert_eq!(true, timer.stats().is_none());
+/// ```
+pub struct SyncTimer<C: Clock = MonotonicClock> {
+    inner: Arc<Inner<C>>,
+}
+
+struct Inner<C: Clock> {
+    clock: C,
+    epoch: C::Instant,
+    /// Time of the latest lap in nanoseconds since `epoch`.
+    last_lap: AtomicU64,
+    samples: Arc<Samples>,
+    exemplars: Mutex<VecDeque<Exemplar>>,
+}
+
+/// A sample together with labels identifying it, e.g. a trace id.
+#[derive(Debug, Clone)]
+pub(crate) struct Exemplar {
+    pub(crate) labels: Vec<(String, String)>,
+    pub(crate) value: Duration,
+    /// Wall clock time of the recording since the Unix epoch.
+    pub(crate) timestamp: Duration,
+}
+
+/// Samples of a [`SyncTimer`], shared with the buffers of all threads.
+struct Samples {
+    shards: Vec<Mutex<Shard>>,
+    buffer_size: AtomicUsize,
+}
+
+struct Shard {
+    /// Allocated on the first sample, as a histogram takes a few hundred KB.
+    histogram: Option<Histogram>,
+    /// The exact samples, if they are kept.
+    samples: Option<Vec<Duration>>,
+}
+
+impl Shard {
+    fn record(&mut self, elapsed: Duration) {
+        self.histogram.get_or_insert_with(Histogram::default).record(elapsed);
+        if let Some(samples) = &mut self.samples {
+            samples.push(elapsed);
+        }
+    }
+}
+
+impl Samples {
+    fn shard(&self) -> &Mutex<Shard> {
+        &self.shards[thread_id() as usize % SHARDS]
+    }
+
+    /// Records all of `samples` at once, so no snapshot contains only a part
+    /// of them.
+    fn append(&self, samples: &mut Vec<Duration>) {
+        let mut shard = lock(self.shard());
+        for sample in samples.drain(..) {
+            shard.record(sample);
+        }
+    }
+}
+
+/// Buffers of the current thread, flushed when the thread exits.
+struct Buffers(Vec<Buffer>);
+
+struct Buffer {
+    samples: Weak<Samples>,
+    pending: Vec<Duration>,
+}
+
+impl Buffers {
+    /// Flushes the buffers accepted by `filter` and forgets those of dropped
+    /// timers.
+    fn flush<F: Fn(&Samples) -> bool>(&mut self, filter: F) {
+        self.0.retain_mut(|buffer| match buffer.samples.upgrade() {
+            Some(samples) => {
+                if filter(&samples) {
+                    samples.append(&mut buffer.pending);
+                }
+                true
+            }
+            None => false,
+        });
+    }
+}
+
+impl Drop for Buffers {
+    fn drop(&mut self) {
+        self.flush(|_| true);
+    }
+}
+
+impl SyncTimer {
+    pub fn new() -> Self {
+        SyncTimer::with_clock(MonotonicClock)
+    }
+}
+
+impl Default for SyncTimer {
+    fn default() -> Self {
+        SyncTimer::new()
+    }
+}
+
+impl<C: Clock> Clone for SyncTimer<C> {
+    fn clone(&self) -> Self {
+        SyncTimer {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<C: Clock> SyncTimer<C> {
+    pub fn with_clock(clock: C) -> Self {
+        SyncTimer {
+            inner: Arc::new(Inner {
+                epoch: clock.now(),
+                clock,
+                last_lap: AtomicU64::new(0),
+                samples: Arc::new(Samples {
+                    shards: (0..SHARDS)
+                        .map(|_| Mutex::new(Shard {
+                            histogram: None,
+                            samples: None,
+                        }))
+                        .collect(),
+                    buffer_size: AtomicUsize::new(256),
+                }),
+                exemplars: Mutex::new(VecDeque::new()),
+            }),
+        }
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.inner.clock
+    }
+
+    /// Records a measured duration.
+    pub fn record(&self, elapsed: Duration) {
+        lock(self.inner.samples.shard()).record(elapsed);
+    }
+
+    /// Records a measured duration and keeps it as exemplar with `labels`,
+    /// e.g. the id of the trace it belongs to. Exemplars are shown by
+    /// [`Registry::render_openmetrics`](crate::Registry::render_openmetrics).
+    pub fn record_exemplar(&self, elapsed: Duration, labels: &[(&str, &str)]) {
+        self.record(elapsed);
+        let mut exemplars = lock(&self.inner.exemplars);
+        if exemplars.len() == EXEMPLARS {
+            exemplars.pop_front();
+        }
+        exemplars.push_back(Exemplar {
+            labels: labels.iter().map(|(label, value)| (label.to_string(), value.to_string())).collect(),
+            value: elapsed,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default(),
+        });
+    }
+
+    /// Records a measured duration into a buffer of the current thread.
+    ///
+    /// The buffer is moved into the timer as a whole once it holds
+    /// [`buffer_size`](SyncTimer::set_buffer_size) samples, on
+    /// [`flush`](SyncTimer::flush) or [`flush_local`] and when the thread
+    /// exits. Until then the samples are missing from
+    /// [`histogram`](SyncTimer::histogram) and [`stats`](SyncTimer::stats).
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::SyncTimer;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let timer = SyncTimer::new();
+    /// timer.set_buffer_size(100);
+    /// let worker = timer.clone();
+    /// thread::spawn(move || {
+    ///     for _ in 0..250 {
+    ///         worker.record_local(Duration::from_millis(1));
+    ///     }
+    ///     assert_eq!(200, worker.count());
+    /// }).join().unwrap();
+    /// assert_eq!(250, timer.count());
+    ///
+    /// timer.record_local(Duration::from_millis(1));
+    /// assert_eq!(250, timer.count());
+    /// timer.flush();
+    /// assert_eq!(251, timer.count());
+    /// ```
+    pub fn record_local(&self, elapsed: Duration) {
+        let samples = &self.inner.samples;
+        let buffered = BUFFERS.try_with(|buffers| {
+            let mut buffers = buffers.borrow_mut();
+            let index = match buffers.0.iter().position(|buffer| buffer.samples.as_ptr() == Arc::as_ptr(samples)) {
+                Some(index) => index,
+                None => {
+                    buffers.0.push(Buffer {
+                        samples: Arc::downgrade(samples),
+                        pending: vec![],
+                    });
+                    buffers.0.len() - 1
+                }
+            };
+            let buffer = &mut buffers.0[index];
+            buffer.pending.push(elapsed);
+            if buffer.pending.len() >= samples.buffer_size.load(Ordering::Relaxed) {
+                samples.append(&mut buffer.pending);
+            }
+        });
+        if buffered.is_err() {
+            // The buffers of this thread are already gone.
+            self.record(elapsed);
+        }
+    }
+
+    /// Runs `f` and records how long it took with
+    /// [`record_local`](SyncTimer::record_local).
+    pub fn time_local<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let clock = &self.inner.clock;
+        let started = clock.now();
+        let value = f();
+        if let Ok(elapsed) = clock.duration_between(started, clock.now()) {
+            self.record_local(elapsed);
+        }
+        value
+    }
+
+    /// Moves the samples buffered by the current thread into the timer.
+    pub fn flush(&self) {
+        let samples = Arc::as_ptr(&self.inner.samples);
+        let _ = BUFFERS.try_with(|buffers| buffers.borrow_mut().flush(|buffered| std::ptr::eq(buffered, samples)));
+    }
+
+    /// Sets the number of samples a thread buffers before moving them into
+    /// the timer, 256 by default.
+    pub fn set_buffer_size(&self, buffer_size: usize) {
+        self.inner.samples.buffer_size.store(buffer_size, Ordering::Relaxed);
+    }
+
+    /// Records the time since the previous lap of any thread, or since the
+    /// creation of the timer, and returns the time since the creation.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, SyncTimer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let timer = SyncTimer::with_clock(clock.clone());
+    /// clock.advance(Duration::from_millis(3));
+    /// assert_eq!(Ok(Duration::from_millis(3)), timer.lap());
+    /// clock.advance(Duration::from_millis(2));
+    /// assert_eq!(Ok(Duration::from_millis(5)), timer.lap());
+    /// assert_eq!(Duration::from_millis(2), timer.histogram().min());
+    /// ```
+    pub fn lap(&self) -> Result<Duration> {
+        let time = self.elapsed()?;
+        let nanos = time.as_nanos().min(u128::from(u64::MAX)) as u64;
+        let previous = self.inner.last_lap.fetch_max(nanos, Ordering::Relaxed);
+        self.record(Duration::from_nanos(nanos.saturating_sub(previous)));
+        Ok(time)
+    }
+
+    /// Time since the creation of the timer.
+    pub fn elapsed(&self) -> Result<Duration> {
+        let inner = &self.inner;
+        inner.clock
+            .duration_between(inner.epoch, inner.clock.now())
+            .map_err(|by| Error::ClockWentBackwards { by })
+    }
+
+    /// Runs `f` and records how long it took.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, SyncTimer};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let timer = SyncTimer::with_clock(clock.clone());
+    /// let rows = timer.time(|| {
+    ///     clock.advance(Duration::from_millis(7));
+    ///     12
+    /// });
+    /// assert_eq!(12, rows);
+    /// assert_eq!(Duration::from_millis(7), timer.histogram().sum());
+    /// ```
+    pub fn time<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let _guard = self.guard();
+        f()
+    }
+
+    /// Returns a guard recording the time until it is dropped.
+    pub fn guard(&self) -> SyncGuard<'_, C> {
+        SyncGuard {
+            timer: self,
+            started: self.inner.clock.now(),
+        }
+    }
+
+    /// Number of recorded samples.
+    pub fn count(&self) -> usize {
+        self.inner.samples.shards.iter()
+            .map(|shard| lock(shard).histogram.as_ref().map_or(0, |histogram| histogram.count() as usize))
+            .sum()
+    }
+
+    /// The samples of all threads recorded so far, in a histogram with the
+    /// default configuration.
+    pub fn histogram(&self) -> Histogram {
+        let mut histogram = Histogram::default();
+        for shard in &self.inner.samples.shards {
+            if let Some(recorded) = &lock(shard).histogram {
+                histogram.merge(recorded);
+            }
+        }
+        histogram
+    }
+
+    /// Keeps the exact samples recorded from now on in addition to the
+    /// histogram, so [`stats`](SyncTimer::stats) can be computed. They take
+    /// memory for every sample, so this is off by default. Switching it off
+    /// drops the kept samples.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::SyncTimer;
+    /// use std::time::Duration;
+    ///
+    /// let timer = SyncTimer::new();
+    /// timer.record(Duration::from_millis(1));
+    /// timer.set_keep_samples(true);
+    /// timer.record(Duration::from_millis(2));
+    /// timer.record(Duration::from_millis(6));
+    ///
+    /// let stats = timer.stats().unwrap();
+    /// assert_eq!(2, stats.count());
+    /// assert_eq!(Some(Duration::from_millis(4)), stats.median());
+    /// assert_eq!(3, timer.count());
+    /// ```
+    pub fn set_keep_samples(&self, keep: bool) {
+        for shard in &self.inner.samples.shards {
+            let mut shard = lock(shard);
+            match (keep, shard.samples.is_some()) {
+                (true, false) => shard.samples = Some(vec![]),
+                (false, true) => shard.samples = None,
+                _ => {}
+            }
+        }
+    }
+
+    /// Statistics over the samples of all threads kept since
+    /// [`set_keep_samples`](SyncTimer::set_keep_samples), or `None` if samples
+    /// are not kept.
+    pub fn stats(&self) -> Option<Stats> {
+        let mut samples = vec![];
+        for shard in &self.inner.samples.shards {
+            samples.extend_from_slice(lock(shard).samples.as_ref()?);
+        }
+        Some(Stats::from_durations(samples))
+    }
+
+    /// Removes all samples and exemplars.
+    pub fn reset(&self) {
+        lock(&self.inner.exemplars).clear();
+        for shard in &self.inner.samples.shards {
+            let mut shard = lock(shard);
+            shard.histogram = None;
+            if let Some(samples) = &mut shard.samples {
+                samples.clear();
+            }
+        }
+    }
+
+    /// The most recent exemplars, oldest first.
+    pub(crate) fn exemplars(&self) -> Vec<Exemplar> {
+        lock(&self.inner.exemplars).iter().cloned().collect()
+    }
+}
+
+/// Moves the samples buffered by the current thread into their
+/// [`SyncTimer`]s, see [`SyncTimer::record_local`].
+pub fn flush_local() {
+    let _ = BUFFERS.try_with(|buffers| buffers.borrow_mut().flush(|_| true));
+}
+
+impl<C: Clock> Sink for SyncTimer<C> {
+    fn record(&self, _name: &str, elapsed: Duration) {
+        SyncTimer::record(self, elapsed);
+    }
+}
+
+/// Guard recording the time from its creation until it is dropped into a
+/// [`SyncTimer`], see [`SyncTimer::guard`].
+#[must_use = "the time is recorded when the guard is dropped"]
+pub struct SyncGuard<'a, C: Clock = MonotonicClock> {
+    timer: &'a SyncTimer<C>,
+    started: C::Instant,
+}
+
+impl<'a, C: Clock> Drop for SyncGuard<'a, C> {
+    fn drop(&mut self) {
+        let clock = &self.timer.inner.clock;
+        if let Ok(elapsed) = clock.duration_between(self.started, clock.now()) {
+            self.timer.record(elapsed);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::{Clock, Profiler, Timer};
+
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    static THREAD: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Small sequential id of the current thread, starting at 1.
+pub(crate) fn thread_id() -> u64 {
+    THREAD.with(|thread| {
+        if thread.get() == 0 {
+            thread.set(NEXT_THREAD.fetch_add(1, Ordering::Relaxed));
+        }
+        thread.get()
+    })
+}
+
+/// Streaming writer of the Trace Event Format (JSON array form) understood by
+/// `chrome://tracing` and <https://ui.perfetto.dev>.
+///
+/// Every event is written as soon as it is added. The closing bracket is
+/// written by [`finish`](TraceWriter::finish), but both viewers also load
+/// traces that end without it, e.g. from a process that was killed.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::TraceWriter;
+/// use std::time::Duration;
+///
+/// let mut trace = TraceWriter::new(vec![]).unwrap().pid(1);
+/// trace.complete("load", 7, Duration::from_micros(10), Duration::from_nanos(1500), &[("rows", "12")]).unwrap();
+/// trace.instant("parsed", 7, Duration::from_micros(11), &[]).unwrap();
+/// let json = String::from_utf8(trace.finish().unwrap()).unwrap();
+/// assert_eq!(
+///     "[\n{\"name\":\"load\",\"ph\":\"X\",\"ts\":10.000,\"dur\":1.500,\"pid\":1,\"tid\":7,\"args\":{\"rows\":\"12\"}},\n\
+///      {\"name\":\"parsed\",\"ph\":\"i\",\"s\":\"t\",\"ts\":11.000,\"pid\":1,\"tid\":7,\"args\":{}}\n]\n",
+///     json
+/// );
+/// ```
+pub struct TraceWriter<W: Write> {
+    writer: W,
+    pid: u32,
+    empty: bool,
+}
+
+impl<W: Write> TraceWriter<W> {
+    /// Starts a trace in `writer`, using the id of the current process as pid.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(b"[\n")?;
+        Ok(TraceWriter {
+            writer,
+            pid: std::process::id(),
+            empty: true,
+        })
+    }
+
+    /// Sets the process id of all following events.
+    pub fn pid(mut self, pid: u32) -> Self {
+        self.pid = pid;
+        self
+    }
+
+    /// Writes a complete (`X`) event lasting `duration` from `start`.
+    pub fn complete(&mut self, name: &str, tid: u64, start: Duration, duration: Duration, args: &[(&str, &str)]) -> io::Result<()> {
+        let event = format!(
+            "{{\"name\":{},\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":{},\"tid\":{},\"args\":{}}}",
+            json_string(name),
+            micros(start),
+            micros(duration),
+            self.pid,
+            tid,
+            json_args(args),
+        );
+        self.event(&event)
+    }
+
+    /// Writes a thread scoped instant (`i`) event at `at`.
+    pub fn instant(&mut self, name: &str, tid: u64, at: Duration, args: &[(&str, &str)]) -> io::Result<()> {
+        let event = format!(
+            "{{\"name\":{},\"ph\":\"i\",\"s\":\"t\",\"ts\":{},\"pid\":{},\"tid\":{},\"args\":{}}}",
+            json_string(name),
+            micros(at),
+            self.pid,
+            tid,
+            json_args(args),
+        );
+        self.event(&event)
+    }
+
+    /// Writes a metadata (`M`) event naming the thread `tid`.
+    pub fn thread_name(&mut self, tid: u64, name: &str) -> io::Result<()> {
+        let event = format!(
+            "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":{},\"tid\":{},\"args\":{{\"name\":{}}}}}",
+            self.pid,
+            tid,
+            json_string(name),
+        );
+        self.event(&event)
+    }
+
+    /// Writes the laps of `timer` as instant events, enclosed by a complete
+    /// event named `name` covering the running time of the timer. Times are
+    /// relative to the start of the timer.
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::{ManualClock, Timer, TraceWriter};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let mut timer = Timer::with_clock(clock.clone());
+    /// timer.start();
+    /// clock.advance(Duration::from_millis(2));
+    /// timer.lap_named("parse");
+    ///
+    /// let mut trace = TraceWriter::new(vec![]).unwrap().pid(1);
+    /// trace.timer("request", 1, &timer).unwrap();
+    /// let json = String::from_utf8(trace.finish().unwrap()).unwrap();
+    /// assert_eq!(true, json.contains("{\"name\":\"request\",\"ph\":\"X\",\"ts\":0.000,\"dur\":2000.000"));
+    /// assert_eq!(true, json.contains("{\"name\":\"parse\",\"ph\":\"i\",\"s\":\"t\",\"ts\":2000.000"));
+    /// ```
+    pub fn timer<C: Clock>(&mut self, name: &str, tid: u64, timer: &Timer<C>) -> io::Result<()> {
+        let elapsed = timer.elapsed().unwrap_or_default();
+        let paused = crate::duration_to_human_string(&timer.paused_time());
+        self.complete(name, tid, Duration::default(), elapsed, &[("paused", &paused)])?;
+        for lap in timer.lap_records() {
+            let index = lap.index().to_string();
+            let split = crate::duration_to_human_string(&lap.split());
+            let mut args = vec![("index", index.as_str()), ("split", split.as_str())];
+            args.extend(lap.tags().iter().map(|(key, value)| (key.as_str(), value.as_str())));
+            self.instant(lap.name().unwrap_or("lap"), tid, lap.time(), &args)?;
+        }
+        Ok(())
+    }
+
+    /// Writes all spans kept by `profiler` (see
+    /// [`Profiler::keep_spans`](crate::Profiler::keep_spans)) as complete events,
+    /// naming the threads they ran on.
+    pub fn profiler<C: Clock>(&mut self, profiler: &Profiler<C>) -> io::Result<()> {
+        let mut named = HashSet::new();
+        for span in profiler.spans() {
+            if let Some(thread_name) = span.thread_name().filter(|_| named.insert(span.thread_id())) {
+                self.thread_name(span.thread_id(), thread_name)?;
+            }
+            self.complete(span.name(), span.thread_id(), span.start(), span.duration(), &[])?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Closes the JSON array and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(b"\n]\n")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn event(&mut self, event: &str) -> io::Result<()> {
+        if !self.empty {
+            self.writer.write_all(b",\n")?;
+        }
+        self.empty = false;
+        self.writer.write_all(event.as_bytes())
+    }
+}
+
+impl<C: Clock> Timer<C> {
+    /// Writes the laps of this timer as a complete Chrome trace, see
+    /// [`TraceWriter::timer`].
+    ///
+    /// # Examples:
+    ///
+    /// ```
+    /// use yatl::Timer;
+    ///
+    /// let mut timer = Timer::new();
+    /// timer.start();
+    /// timer.lap();
+    /// let json = String::from_utf8(timer.write_chrome_trace(vec![]).unwrap()).unwrap();
+    /// assert_eq!(true, json.starts_with("[\n{\"name\":\"timer\",\"ph\":\"X\""));
+    /// ```
+    pub fn write_chrome_trace<W: Write>(&self, writer: W) -> io::Result<W> {
+        let mut trace = TraceWriter::new(writer)?;
+        trace.timer("timer", thread_id(), self)?;
+        trace.finish()
+    }
+}
+
+/// Microseconds with three decimals, as expected for `ts` and `dur`.
+fn micros(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+    format!("{}.{:03}", nanos / 1000, nanos % 1000)
+}
+
+fn json_args(args: &[(&str, &str)]) -> String {
+    let fields: Vec<String> = args.iter()
+        .map(|(key, value)| format!("{}:{}", json_string(key), json_string(value)))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
@@ -0,0 +1,166 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, Expr, ExprLit, ItemFn, Lit, MetaNameValue, Token};
+use yatl_parse::parse_duration;
+
+/// Measures every call of a function and hands the duration to a sink.
+///
+/// The duration is recorded under the path of the function
+/// (`module::path::function`), by default into the timer of that name in
+/// `yatl::registry()`. Works for sync and `async` functions; for the latter
+/// the measurement covers the first poll until completion.
+///
+/// Optional arguments:
+/// - `name = "..."` records under a different name.
+/// - `threshold = "10ms"` only records calls taking at least this long. Takes
+///   a string parsed at compile time by the same parser as
+///   `yatl::parse_duration`, or a `Duration` expression.
+/// - `sink = expr` hands measurements to any `yatl::Sink` instead, e.g.
+///   `yatl::StderrSink` to print them.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::timed;
+/// use std::sync::Mutex;
+/// use std::time::Duration;
+///
+/// static CALLS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+///
+/// fn record(name: &str, _elapsed: Duration) {
+///     CALLS.lock().unwrap().push(name.to_string());
+/// }
+///
+/// #[timed(sink = record)]
+/// fn parse(input: &str) -> Result<u32, std::num::ParseIntError> {
+///     Ok(input.parse::<u32>()? * 2)
+/// }
+///
+/// #[timed(name = "slow", threshold = "1h", sink = record)]
+/// fn fast() {}
+///
+/// assert_eq!(Ok(42), parse("21"));
+/// assert_eq!(true, parse("x").is_err());
+/// fast();
+/// assert_eq!(vec!["rust_out::parse", "rust_out::parse"], *CALLS.lock().unwrap());
+/// ```
+///
+/// ## Recording into the registry:
+/// ```
+/// use yatl::timed;
+///
+/// #[timed]
+/// fn load() {}
+///
+/// load();
+/// load();
+/// assert_eq!(2, yatl::global("rust_out::load").count());
+/// ```
+///
+/// ## Async functions:
+/// ```
+/// use yatl::timed;
+/// use std::future::Future;
+/// use std::pin::pin;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use std::task::{Context, Poll, Wake, Waker};
+/// use std::time::Duration;
+///
+/// struct Noop;
+///
+/// impl Wake for Noop {
+///     fn wake(self: Arc<Self>) {}
+/// }
+///
+/// static RECORDED: AtomicUsize = AtomicUsize::new(0);
+///
+/// #[timed(name = "fetch", sink = |_: &str, _: Duration| { RECORDED.fetch_add(1, Ordering::SeqCst); })]
+/// async fn fetch(id: u32) -> u32 {
+///     id + 1
+/// }
+///
+/// let future = fetch(1);
+/// assert_eq!(0, RECORDED.load(Ordering::SeqCst));
+/// let mut future = pin!(future);
+/// let waker = Waker::from(Arc::new(Noop));
+/// let mut context = Context::from_waker(&waker);
+/// assert_eq!(Poll::Ready(2), future.as_mut().poll(&mut context));
+/// assert_eq!(1, RECORDED.load(Ordering::SeqCst));
+/// ```
+///
+/// ## Threshold grammar:
+/// ```
+/// use yatl::{parse_duration, timed, StderrSink};
+///
+/// #[timed(threshold = "1h 02m 03.4s", sink = StderrSink)]
+/// fn compound() {}
+///
+/// #[timed(threshold = "13.5µs", sink = StderrSink)]
+/// fn micro_sign() {}
+///
+/// #[timed(threshold = " 250 ms", sink = StderrSink)]
+/// fn spaced() {}
+///
+/// for threshold in ["1h 02m 03.4s", "13.5µs", " 250 ms"] {
+///     assert_eq!(true, parse_duration(threshold).is_ok());
+/// }
+/// ```
+///
+/// ## Invalid thresholds:
+/// ```compile_fail
+/// #[yatl::timed(threshold = "10 parsecs")]
+/// fn far() {}
+/// ```
+#[proc_macro_attribute]
+pub fn timed(args: TokenStream, item: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(item as ItemFn);
+    let args = match Punctuated::<MetaNameValue, Token![,]>::parse_terminated.parse(args) {
+        Ok(args) => args,
+        Err(error) => return error.to_compile_error().into(),
+    };
+    match expand(args, function) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(args: Punctuated<MetaNameValue, Token![,]>, function: ItemFn) -> syn::Result<TokenStream2> {
+    let ident = &function.sig.ident;
+    let mut name = quote!(concat!(module_path!(), "::", stringify!(#ident)));
+    let mut threshold = quote!(::std::option::Option::None);
+    let mut sink = quote!(::yatl::registry());
+    for arg in args {
+        let value = arg.value;
+        if arg.path.is_ident("name") {
+            name = quote!(#value);
+        } else if arg.path.is_ident("threshold") {
+            threshold = match value {
+                Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => {
+                    let duration = parse_duration(&lit.value())
+                        .map_err(|error| syn::Error::new(lit.span(), format!("Invalid threshold: {}", error)))?;
+                    let secs = duration.as_secs();
+                    let subsec_nanos = duration.subsec_nanos();
+                    quote!(::std::option::Option::Some(::std::time::Duration::new(#secs, #subsec_nanos)))
+                }
+                value => quote!(::std::option::Option::Some(#value)),
+            };
+        } else if arg.path.is_ident("sink") {
+            sink = quote!(#value);
+        } else {
+            return Err(syn::Error::new_spanned(arg.path, "Expected `name`, `threshold` or `sink`"));
+        }
+    }
+
+    let ItemFn { attrs, vis, sig, block } = function;
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            let __yatl_timed = ::yatl::Timed::new(#name, #threshold, #sink);
+            #block
+        }
+    })
+}
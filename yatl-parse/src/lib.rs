@@ -0,0 +1,216 @@
+//! Parser for human readable durations, used by `yatl` at runtime and by the
+//! `#[timed]` attribute of `yatl-macros` at compile time, which cannot depend
+//! on `yatl` itself.
+
+use std::fmt;
+use std::time::Duration;
+
+/// Why parsing a duration failed, see [`ParseError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The input holds nothing but whitespace.
+    Empty,
+    /// A number was expected.
+    ExpectedNumber,
+    /// A number is not followed by a unit.
+    MissingUnit,
+    /// The unit is not one of `ns`, `us`, `µs`, `ms`, `s`, `m`, `h`, `d` or `w`.
+    UnknownUnit,
+    /// The duration does not fit into a [`Duration`].
+    Overflow,
+}
+
+/// Error returned by [`parse_duration`], pointing at the byte offset in the
+/// input where parsing failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    position: usize,
+}
+
+impl ParseError {
+    pub fn kind(&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    /// Byte offset in the input at which the error was found.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self.kind {
+            ParseErrorKind::Empty => "empty duration",
+            ParseErrorKind::ExpectedNumber => "expected a number",
+            ParseErrorKind::MissingUnit => "missing unit",
+            ParseErrorKind::UnknownUnit => "unknown unit",
+            ParseErrorKind::Overflow => "duration too long",
+        };
+        write!(f, "{} at position {}", message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// Most fractional digits taken into account. Later digits are below a
+/// nanosecond for every unit and are ignored.
+const MAX_FRACTION_DIGITS: u32 = 18;
+
+/// Parses a human readable duration such as `250ms`, `13.5us` or `1h 30m`.
+///
+/// Accepts every unit `yatl::DurationFormatter` emits
+/// (`ns`, `us`, `µs`, `ms`, `s`, `m`, `h`, `d`, `w`), decimal numbers and
+/// sequences of several components, optionally separated by whitespace.
+/// Fractions of a nanosecond are truncated.
+///
+/// # Examples:
+///
+/// ```
+/// use yatl::parse_duration;
+/// use std::time::Duration;
+///
+/// assert_eq!(Ok(Duration::from_secs(5400)), parse_duration("1h30m"));
+/// assert_eq!(Ok(Duration::from_millis(250)), parse_duration("250ms"));
+/// assert_eq!(Ok(Duration::from_nanos(13_500)), parse_duration("13.5us"));
+/// assert_eq!(Ok(Duration::from_millis(3_723_400)), parse_duration("1h 02m 03.4s"));
+/// ```
+///
+/// ## Errors:
+/// ```
+/// use yatl::{parse_duration, ParseErrorKind};
+///
+/// let error = parse_duration("1h 30x").unwrap_err();
+/// assert_eq!(ParseErrorKind::UnknownUnit, error.kind());
+/// assert_eq!(5, error.position());
+/// assert_eq!(ParseErrorKind::MissingUnit, parse_duration("12").unwrap_err().kind());
+/// assert_eq!(ParseErrorKind::ExpectedNumber, parse_duration("ms").unwrap_err().kind());
+/// assert_eq!(ParseErrorKind::Empty, parse_duration("  ").unwrap_err().kind());
+/// ```
+///
+/// ## Round trip through the formatter:
+/// ```
+/// use quickcheck::quickcheck;
+/// use yatl::{parse_duration, DurationFormatter, Rounding};
+/// use std::time::Duration;
+///
+/// fn round_trips(secs: u64, nanos: u32, precision: u8, rounding: u8, compound: bool, micro_sign: bool) -> bool {
+///     let duration = Duration::new(secs % 10_000_000, nanos % 1_000_000_000);
+///     let precision = usize::from(precision % 10);
+///     let rounding = [Rounding::Truncate, Rounding::Nearest, Rounding::Ceiling][usize::from(rounding % 3)];
+///     let formatter = DurationFormatter::new()
+///         .precision(precision)
+///         .rounding(rounding)
+///         .compound(compound)
+///         .micro_sign(micro_sign)
+///         .aligned(20);
+///     let parsed = parse_duration(&formatter.format(&duration)).unwrap();
+///     let difference = if parsed > duration { parsed - duration } else { duration - parsed };
+///     difference.as_nanos() <= duration.max(parsed).as_nanos() / 10u128.pow(precision as u32)
+/// }
+///
+/// quickcheck(round_trips as fn(u64, u32, u8, u8, bool, bool) -> bool);
+/// ```
+pub fn parse_duration(input: &str) -> Result<Duration, ParseError> {
+    let mut parser = Parser { input, position: 0 };
+    parser.skip_whitespace();
+    if parser.at_end() {
+        return Err(parser.error(ParseErrorKind::Empty));
+    }
+    let mut total: u128 = 0;
+    while !parser.at_end() {
+        let nanos = parser.component()?;
+        total = total.checked_add(nanos).ok_or_else(|| parser.error(ParseErrorKind::Overflow))?;
+        parser.skip_whitespace();
+    }
+    let secs = total / NANOS_PER_SEC;
+    if secs > u128::from(u64::MAX) {
+        return Err(parser.error(ParseErrorKind::Overflow));
+    }
+    Ok(Duration::new(secs as u64, (total % NANOS_PER_SEC) as u32))
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Parses a number followed by a unit and returns it in nanoseconds.
+    fn component(&mut self) -> Result<u128, ParseError> {
+        let start = self.position;
+        let integer = self.take_while(|c| c.is_ascii_digit());
+        if integer.is_empty() {
+            return Err(self.error(ParseErrorKind::ExpectedNumber));
+        }
+        let fraction = if self.rest().starts_with('.') {
+            self.position += 1;
+            let fraction = self.take_while(|c| c.is_ascii_digit());
+            if fraction.is_empty() {
+                return Err(self.error(ParseErrorKind::ExpectedNumber));
+            }
+            fraction
+        } else {
+            ""
+        };
+        self.skip_whitespace();
+        let unit_position = self.position;
+        let unit: u128 = match self.take_while(char::is_alphabetic) {
+            "" => return Err(self.error(ParseErrorKind::MissingUnit)),
+            "ns" => 1,
+            "us" | "µs" => 1_000,
+            "ms" => 1_000_000,
+            "s" => NANOS_PER_SEC,
+            "m" => 60 * NANOS_PER_SEC,
+            "h" => 60 * 60 * NANOS_PER_SEC,
+            "d" => 24 * 60 * 60 * NANOS_PER_SEC,
+            "w" => 7 * 24 * 60 * 60 * NANOS_PER_SEC,
+            _ => {
+                return Err(ParseError {
+                    kind: ParseErrorKind::UnknownUnit,
+                    position: unit_position,
+                })
+            }
+        };
+
+        let overflow = ParseError {
+            kind: ParseErrorKind::Overflow,
+            position: start,
+        };
+        let integer: u128 = integer.parse().map_err(|_| overflow)?;
+        let digits = (fraction.len() as u32).min(MAX_FRACTION_DIGITS);
+        let fraction: u128 = if digits == 0 { 0 } else { fraction[..digits as usize].parse().map_err(|_| overflow)? };
+        let whole = integer.checked_mul(unit).ok_or(overflow)?;
+        let part = fraction * unit / 10u128.pow(digits);
+        whole.checked_add(part).ok_or(overflow)
+    }
+
+    fn take_while<P: Fn(char) -> bool>(&mut self, predicate: P) -> &'a str {
+        let rest = self.rest();
+        let length = rest.find(|c| !predicate(c)).unwrap_or(rest.len());
+        self.position += length;
+        &rest[..length]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn at_end(&self) -> bool {
+        self.position == self.input.len()
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            position: self.position,
+        }
+    }
+}